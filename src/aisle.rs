@@ -0,0 +1,295 @@
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
+use cooklang::ingredient_list::IngredientList;
+use tracing::warn;
+use yansi::Paint;
+
+use crate::{
+    util::{find_recipe_files, parse_aisle, parse_recipe, Scaling},
+    Context, AUTO_AISLE, LOCAL_CONFIG_DIR,
+};
+
+#[derive(Debug, Args)]
+pub struct AisleArgs {
+    #[command(subcommand)]
+    command: AisleCommand,
+
+    /// Aisle conf file, defaults to `aisle.conf` from the config dir
+    #[arg(short, long, global = true)]
+    aisle: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum AisleCommand {
+    /// Check the aisle file for errors
+    #[command(alias = "c")]
+    Check,
+
+    /// List ingredients of the collection without a category
+    #[command(alias = "m")]
+    Missing,
+
+    /// Add an ingredient to a category
+    ///
+    /// The category is created at the end of the file if it doesn't exist.
+    /// The rest of the file is kept as it is.
+    Add(AddArgs),
+}
+
+#[derive(Debug, Args)]
+struct AddArgs {
+    /// Ingredient name, synonyms can be added separated by `|`
+    ingredient: String,
+
+    /// Category name
+    category: String,
+}
+
+pub fn run(ctx: &Context, args: AisleArgs) -> Result<()> {
+    match args.command {
+        AisleCommand::Check => check(&aisle_path(ctx, args.aisle)?),
+        AisleCommand::Missing => missing(ctx, &aisle_path(ctx, args.aisle)?),
+        AisleCommand::Add(add_args) => {
+            let path = args
+                .aisle
+                .or_else(|| ctx.aisle())
+                .unwrap_or_else(|| ctx.base_path().join(LOCAL_CONFIG_DIR).join(AUTO_AISLE));
+            add(&path, add_args)
+        }
+    }
+}
+
+fn aisle_path(ctx: &Context, path: Option<Utf8PathBuf>) -> Result<Utf8PathBuf> {
+    path.or_else(|| ctx.aisle()).context(
+        "No aisle file found. Create config/aisle.conf or use --aisle <file>. \
+         Docs https://cooklang.org/docs/spec/#shopping-lists",
+    )
+}
+
+fn read(path: &Utf8Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read aisle file: {path}"))
+}
+
+fn check(path: &Utf8Path) -> Result<()> {
+    let content = read(path)?;
+    let aisle = parse_aisle(path, &content)?;
+
+    let ingredients = aisle.ingredients_info().len();
+    let categories = aisle.categories.len();
+    println!(
+        "{} {path}: {categories} categories, {ingredients} ingredient names",
+        "ok".green().bold()
+    );
+    for category in aisle.categories.iter().filter(|c| c.ingredients.is_empty()) {
+        warn!("Empty category: '{}'", category.name);
+    }
+    Ok(())
+}
+
+fn missing(ctx: &Context, path: &Utf8Path) -> Result<()> {
+    let content = read(path)?;
+    let aisle = parse_aisle(path, &content)?;
+    let known = aisle.ingredients_info();
+
+    let parser = ctx.parser()?;
+    // ingredient -> recipes using it
+    let mut missing: BTreeMap<String, Vec<Utf8PathBuf>> = BTreeMap::new();
+    for file in find_recipe_files(ctx.base_path())? {
+        let content = std::fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read recipe file: {file}"))?;
+        let recipe = match parse_recipe(&content, parser, Scaling::Factor(1.0)) {
+            Ok(recipe) => recipe,
+            Err(e) => {
+                warn!("Skipping {file}: {e:#}");
+                continue;
+            }
+        };
+        let recipe_path = file
+            .strip_prefix(ctx.base_path())
+            .unwrap_or(&file)
+            .to_path_buf();
+        for (name, _) in IngredientList::from_recipe(&recipe, parser.converter(), false) {
+            if !known.contains_key(name.as_str()) {
+                missing.entry(name).or_default().push(recipe_path.clone());
+            }
+        }
+    }
+
+    let mut table = tabular::Table::new("{:<}  {:<}");
+    for (name, recipes) in missing {
+        let recipes = recipes
+            .iter()
+            .map(|r| r.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(name)
+                .with_ansi_cell(recipes.dim()),
+        );
+    }
+    write!(std::io::stdout().lock(), "{table}")?;
+    Ok(())
+}
+
+fn add(path: &Utf8Path, args: AddArgs) -> Result<()> {
+    let ingredient = args.ingredient.trim();
+    let category = args.category.trim();
+    if ingredient.is_empty() || category.is_empty() {
+        bail!("The ingredient and the category can't be empty");
+    }
+
+    let content = if path.exists() {
+        read(path)?
+    } else {
+        String::new()
+    };
+    let aisle = parse_aisle(path, &content)?;
+    let known = aisle.ingredients_info();
+    for name in ingredient.split('|').map(str::trim) {
+        // the shopping list doesn't ignore case, but two names that only
+        // differ in it are surely a mistake
+        if let Some(info) = known
+            .values()
+            .find(|info| info.name.to_lowercase() == name.to_lowercase())
+        {
+            bail!("'{}' is already in category '{}'", info.name, info.category);
+        }
+    }
+
+    let updated = insert_ingredient(&content, ingredient, category);
+    // make sure the result is still valid, like a category name with `|`
+    parse_aisle(path, &updated)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {parent}"))?;
+    }
+    std::fs::write(path, updated).with_context(|| format!("Failed to write aisle file: {path}"))?;
+    println!("Added '{ingredient}' to [{category}] in {path}");
+    Ok(())
+}
+
+/// Adds a line after the last ingredient of the category, or the category at
+/// the end of the file
+///
+/// The category is found ignoring case and the line endings of the file are
+/// kept.
+fn insert_ingredient(content: &str, ingredient: &str, category: &str) -> String {
+    let lines = content.lines().collect::<Vec<_>>();
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut out = String::with_capacity(content.len() + ingredient.len() + newline.len());
+    let is_category =
+        |line: &str| header(line).is_some_and(|h| h.to_lowercase() == category.to_lowercase());
+    match lines.iter().position(|l| is_category(l)) {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| header(l).is_some())
+                .map_or(lines.len(), |i| start + 1 + i);
+            // keep blank lines and comments before the next category after it
+            let last = (start..end)
+                .rev()
+                .find(|&i| {
+                    let l = lines[i].trim();
+                    !l.is_empty() && !l.starts_with("//")
+                })
+                .unwrap_or(start);
+            for (i, line) in lines.iter().enumerate() {
+                out += line;
+                out += newline;
+                if i == last {
+                    out += ingredient;
+                    out += newline;
+                }
+            }
+        }
+        None => {
+            for line in &lines {
+                out += line;
+                out += newline;
+            }
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                out += newline;
+            }
+            out += &format!("[{category}]{newline}{ingredient}{newline}");
+        }
+    }
+    out
+}
+
+/// Category name if the line is a category header
+fn header(line: &str) -> Option<&str> {
+    let line = line.split_once("//").map_or(line, |(l, _)| l).trim();
+    line.strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AISLE: &str = "[Dairy]\nmilk\nbutter|unsalted butter\n\n// baking\n[Baking]\nflour\n";
+
+    #[test]
+    fn adds_to_the_category() {
+        assert_eq!(
+            insert_ingredient(AISLE, "cheese", "Dairy"),
+            "[Dairy]\nmilk\nbutter|unsalted butter\ncheese\n\n// baking\n[Baking]\nflour\n"
+        );
+        assert_eq!(
+            insert_ingredient(AISLE, "sugar|caster sugar", "baking"),
+            format!("{AISLE}sugar|caster sugar\n")
+        );
+    }
+
+    #[test]
+    fn adds_the_category_at_the_end() {
+        assert_eq!(
+            insert_ingredient(AISLE, "apples", "Fruit"),
+            format!("{AISLE}\n[Fruit]\napples\n")
+        );
+        assert_eq!(
+            insert_ingredient("", "apples", "Fruit"),
+            "[Fruit]\napples\n"
+        );
+        // a missing final newline is added
+        assert_eq!(
+            insert_ingredient("[Dairy]\nmilk", "apples", "Fruit"),
+            "[Dairy]\nmilk\n\n[Fruit]\napples\n"
+        );
+    }
+
+    #[test]
+    fn keeps_crlf() {
+        let crlf = AISLE.replace('\n', "\r\n");
+        let updated = insert_ingredient(&crlf, "cheese", "Dairy");
+        assert_eq!(
+            updated.matches('\n').count(),
+            updated.matches("\r\n").count()
+        );
+        assert!(updated.contains("butter\r\ncheese\r\n"));
+
+        let updated = insert_ingredient(&crlf, "apples", "Fruit");
+        assert!(updated.ends_with("flour\r\n\r\n[Fruit]\r\napples\r\n"));
+    }
+
+    #[test]
+    fn result_parses() {
+        let updated = insert_ingredient(AISLE, "cheese|cheddar", "Dairy");
+        let aisle = parse_aisle(Utf8Path::new("aisle.conf"), &updated).unwrap();
+        let info = aisle.ingredients_info();
+        assert_eq!(info["cheddar"].category, "Dairy");
+        assert_eq!(info["cheddar"].common_name, "cheese");
+        assert_eq!(info["flour"].category, "Baking");
+    }
+}
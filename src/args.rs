@@ -30,7 +30,7 @@
 
 use clap::{Parser, Subcommand};
 
-use crate::{import, recipe, report, search, seed, server, shopping_list};
+use crate::{aisle, import, plan, recipe, report, search, seed, server, shopping_list};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -58,12 +58,21 @@ pub enum Command {
     #[command(visible_alias = "sl")]
     ShoppingList(shopping_list::ShoppingListArgs),
 
+    /// Work with meal plan files
+    #[command(alias = "p")]
+    Plan(plan::PlanArgs),
+
+    /// Check and edit the aisle file used to categorize shopping lists
+    #[command(alias = "a")]
+    Aisle(aisle::AisleArgs),
+
     /// Populate directory with seed recipes
     #[command()]
     Seed(seed::SeedArgs),
 
     /// Search for recipes containing the given text.
-    /// Multiple search terms are supported, separated by spaces.
+    /// Multiple search terms are supported, separated by spaces, as well as
+    /// filters like `ingredient:garlic` or `time<30m`.
     /// Results are sorted by relevance.
     #[command(alias = "f")]
     Search(search::SearchArgs),
@@ -0,0 +1,182 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    convert::{ConverterBuilder, System, UnitsFile},
+    Converter, CooklangParser, Extensions,
+};
+use serde::Deserialize;
+
+use crate::util::cooklang_to_md;
+
+/// Project configuration loaded from `config/cook.toml`
+///
+/// Every section is optional, missing values fall back to the same defaults
+/// the command line uses. Command line arguments always take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub parser: ParserConfig,
+    pub recipe: RecipeConfig,
+    pub shopping_list: ShoppingListConfig,
+    /// Options used when writing recipes as markdown
+    pub markdown: cooklang_to_md::Options,
+    pub server: ServerConfig,
+    pub import: ImportConfig,
+}
+
+impl Config {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {path}"))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse config file: {path}"))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParserConfig {
+    /// Cooklang extensions to enable
+    ///
+    /// Either a preset (`canonical`, `compat` or `all`) or a list of
+    /// extension names.
+    pub extensions: ExtensionsConfig,
+    /// Units known to the parser
+    pub units: UnitsConfig,
+    /// Default unit system, only used with bundled units
+    pub system: Option<System>,
+}
+
+impl ParserConfig {
+    pub fn build_parser(&self) -> Result<CooklangParser> {
+        let converter = match self.units {
+            UnitsConfig::None => Converter::empty(),
+            UnitsConfig::Bundled => {
+                let mut builder = ConverterBuilder::new().with_bundled_units()?;
+                if self.system.is_some() {
+                    builder = builder.with_units_file(UnitsFile {
+                        default_system: self.system,
+                        si: None,
+                        fractions: None,
+                        extend: None,
+                        quantity: Vec::new(),
+                    })?;
+                }
+                builder.finish()?
+            }
+        };
+
+        Ok(CooklangParser::new(self.extensions.extensions(), converter))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ExtensionsConfig {
+    Preset(ExtensionsPreset),
+    List(Vec<Extension>),
+}
+
+impl Default for ExtensionsConfig {
+    fn default() -> Self {
+        Self::Preset(ExtensionsPreset::Canonical)
+    }
+}
+
+impl ExtensionsConfig {
+    fn extensions(&self) -> Extensions {
+        match self {
+            Self::Preset(ExtensionsPreset::Canonical) => Extensions::empty(),
+            Self::Preset(ExtensionsPreset::Compat) => Extensions::COMPAT,
+            Self::Preset(ExtensionsPreset::All) => Extensions::all(),
+            Self::List(list) => list
+                .iter()
+                .fold(Extensions::empty(), |acc, e| acc | e.extension()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtensionsPreset {
+    Canonical,
+    Compat,
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Extension {
+    ComponentModifiers,
+    ComponentAlias,
+    AdvancedUnits,
+    Modes,
+    InlineQuantities,
+    RangeValues,
+    TimerRequiresTime,
+    IntermediatePreparations,
+}
+
+impl Extension {
+    fn extension(&self) -> Extensions {
+        match self {
+            Self::ComponentModifiers => Extensions::COMPONENT_MODIFIERS,
+            Self::ComponentAlias => Extensions::COMPONENT_ALIAS,
+            Self::AdvancedUnits => Extensions::ADVANCED_UNITS,
+            Self::Modes => Extensions::MODES,
+            Self::InlineQuantities => Extensions::INLINE_QUANTITIES,
+            Self::RangeValues => Extensions::RANGE_VALUES,
+            Self::TimerRequiresTime => Extensions::TIMER_REQUIRES_TIME,
+            Self::IntermediatePreparations => Extensions::INTERMEDIATE_PREPARATIONS,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitsConfig {
+    /// Don't use any units, like the canonical parser
+    #[default]
+    None,
+    /// Units bundled with the parser
+    Bundled,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecipeConfig {
+    /// Scale used when none is given
+    pub scale: Option<f64>,
+    /// Default output format for `recipe read`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShoppingListConfig {
+    /// Default output format for `shopping-list`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Allow external connections
+    pub host: bool,
+    /// Http server port
+    pub port: Option<u16>,
+    /// Open browser on start
+    pub open: bool,
+    /// Allow creating, changing and removing recipes through the API
+    pub edit: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImportConfig {
+    /// LLM provider to convert recipes: `openai`, `anthropic` or `stub`
+    pub provider: Option<String>,
+    /// Model of the provider, each one has a default
+    pub model: Option<String>,
+    /// Base URL of the API, for OpenAI compatible servers like Ollama
+    pub base_url: Option<String>,
+}
@@ -0,0 +1,164 @@
+//! Metadata of imported recipes, from the schema.org recipe of the page
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::schema::strings;
+
+/// What the recipe of `cooklang-import` leaves out
+#[derive(Debug, Default)]
+pub struct Metadata {
+    /// URL of the recipe
+    pub source: Option<String>,
+    pub servings: Option<u32>,
+    pub prep_time: Option<u32>,
+    pub cook_time: Option<u32>,
+    pub tags: Vec<String>,
+}
+
+impl Metadata {
+    /// Reads the metadata of a schema.org `Recipe`
+    pub fn from_schema(recipe: &Value) -> Self {
+        let mut tags = Vec::new();
+        for key in ["keywords", "recipeCategory", "recipeCuisine"] {
+            for value in strings(&recipe[key]) {
+                for tag in value.split(',') {
+                    let tag = tag.trim().to_lowercase();
+                    if !tag.is_empty() && !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+            }
+        }
+
+        Self {
+            source: recipe["url"].as_str().map(String::from),
+            servings: strings(&recipe["recipeYield"]).find_map(leading_number),
+            prep_time: recipe["prepTime"].as_str().and_then(iso_minutes),
+            cook_time: recipe["cookTime"].as_str().and_then(iso_minutes),
+            tags,
+        }
+    }
+
+    /// Metadata entries for the frontmatter
+    pub fn to_yaml(&self) -> serde_yaml::Mapping {
+        let mut map = serde_yaml::Mapping::new();
+        let mut insert = |key: &str, value: serde_yaml::Value| {
+            map.insert(key.into(), value);
+        };
+        if let Some(source) = &self.source {
+            insert("source", source.as_str().into());
+        }
+        if let Some(servings) = self.servings {
+            insert("servings", servings.into());
+        }
+        if let Some(minutes) = self.prep_time {
+            insert("prep time", format_minutes(minutes).into());
+        }
+        if let Some(minutes) = self.cook_time {
+            insert("cook time", format_minutes(minutes).into());
+        }
+        if !self.tags.is_empty() {
+            insert("tags", self.tags.clone().into());
+        }
+        map
+    }
+}
+
+/// `4` from `4 servings`
+fn leading_number(s: String) -> Option<u32> {
+    let s = s.trim();
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok().filter(|&n| n > 0)
+}
+
+/// Minutes of an ISO 8601 duration like `PT1H30M`, `None` if it's 0
+fn iso_minutes(duration: &str) -> Option<u32> {
+    let duration = duration.strip_prefix('P')?;
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in duration.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            _ => {
+                let value: f64 = std::mem::take(&mut number).parse().ok()?;
+                minutes += value
+                    * match (c, in_time) {
+                        ('D', false) => 24.0 * 60.0,
+                        ('H', true) => 60.0,
+                        ('M', true) => 1.0,
+                        ('S', true) => 1.0 / 60.0,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    let minutes = minutes.round() as u32;
+    (minutes > 0).then_some(minutes)
+}
+
+/// Like `1h 30m`, which the parser understands
+fn format_minutes(minutes: u32) -> String {
+    humantime::format_duration(Duration::from_secs(u64::from(minutes) * 60)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn iso_durations() {
+        assert_eq!(iso_minutes("PT30M"), Some(30));
+        assert_eq!(iso_minutes("PT1H30M"), Some(90));
+        assert_eq!(iso_minutes("PT2H"), Some(120));
+        assert_eq!(iso_minutes("P1DT2H"), Some(26 * 60));
+        assert_eq!(iso_minutes("PT90S"), Some(2));
+        assert_eq!(iso_minutes("PT1.5H"), Some(90));
+
+        assert_eq!(iso_minutes("PT0M"), None);
+        assert_eq!(iso_minutes("PT"), None);
+        assert_eq!(iso_minutes("30 minutes"), None);
+        // months, not minutes
+        assert_eq!(iso_minutes("P1M"), None);
+        assert_eq!(iso_minutes("PTM"), None);
+    }
+
+    #[test]
+    fn leading_numbers() {
+        assert_eq!(leading_number("4".into()), Some(4));
+        assert_eq!(leading_number(" 4 servings".into()), Some(4));
+        assert_eq!(leading_number("12-16 cookies".into()), Some(12));
+        assert_eq!(leading_number("0".into()), None);
+        assert_eq!(leading_number("serves 4".into()), None);
+        assert_eq!(leading_number("".into()), None);
+    }
+
+    #[test]
+    fn from_schema() {
+        let metadata = Metadata::from_schema(&json!({
+            "url": "https://example.com/pancakes",
+            "recipeYield": ["4", "4 servings"],
+            "prepTime": "PT10M",
+            "cookTime": "PT1H5M",
+            "keywords": "Breakfast, sweet",
+            "recipeCategory": ["breakfast", "Dessert"],
+        }));
+        assert_eq!(metadata.servings, Some(4));
+        assert_eq!(metadata.tags, ["breakfast", "sweet", "dessert"]);
+
+        let yaml = serde_yaml::to_string(&metadata.to_yaml()).unwrap();
+        assert_eq!(
+            yaml,
+            "source: https://example.com/pancakes\nservings: 4\nprep time: 10m\n\
+             cook time: 1h 5m\ntags:\n- breakfast\n- sweet\n- dessert\n"
+        );
+
+        let empty = Metadata::from_schema(&json!({ "recipeYield": "a few" }));
+        assert!(empty.to_yaml().is_empty());
+    }
+}
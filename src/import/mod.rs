@@ -0,0 +1,364 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::CooklangParser;
+use cooklang_import::{
+    extractors::{Extractor, JsonLdExtractor, ParsingContext, PlainTextLlmExtractor},
+    model::Recipe,
+};
+use reqwest::header::USER_AGENT;
+use scraper::Html;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{util::config_format, Context};
+
+mod metadata;
+mod provider;
+mod rules;
+mod save;
+mod schema;
+mod validate;
+
+use metadata::Metadata;
+use provider::{Provider, ProviderKind, ProviderOptions};
+use validate::Validation;
+
+/// Prompt for every provider, with the `{name}`, `{ingredients}` and
+/// `{instructions}` of the recipe
+const PROMPT: &str = include_str!("prompt.txt");
+
+/// Added to the prompt to retry a conversion with the `{converted}` recipe
+/// and its `{errors}`
+const REPAIR_PROMPT: &str = include_str!("repair.txt");
+
+/// Some sites don't answer to unknown clients
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// URL of the recipe to import
+    ///
+    /// It can also be a saved page or a JSON-LD file, or `-` to read any of
+    /// them from stdin. Those are read without any network.
+    #[arg(value_name = "URL|FILE")]
+    source: String,
+
+    /// Convert the recipe with simple rules instead of an LLM
+    ///
+    /// Quantities, units and preparations are taken from the ingredient
+    /// list and the ingredients are marked where the instructions first
+    /// mention them. No API key is needed.
+    #[arg(short, long)]
+    skip_conversion: bool,
+
+    /// LLM used to convert the recipe
+    ///
+    /// Defaults to `import.provider` from the config file or "openai". The
+    /// API keys are read from `OPENAI_API_KEY` and `ANTHROPIC_API_KEY`.
+    #[arg(short, long, value_enum)]
+    provider: Option<ProviderKind>,
+
+    /// Model of the provider
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Base URL of the API, like `http://localhost:11434/v1` for Ollama
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// Same as `--provider anthropic`
+    #[arg(long, hide = true, conflicts_with = "provider")]
+    use_claude: bool,
+
+    /// Times to retry the conversion, giving the parse errors back to the
+    /// model, if the result is not valid Cooklang
+    #[arg(short, long, default_value_t = 0)]
+    retries: u32,
+
+    /// Save the recipe in this directory of the collection instead of
+    /// printing it
+    ///
+    /// Without a directory it's saved in the base path, as `<Recipe Name>.cook`
+    /// with the image of the recipe next to it. The directory goes after an
+    /// `=`, like `--output=Desserts`.
+    #[arg(
+        short,
+        long,
+        visible_alias = "save-to",
+        value_name = "DIR",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    output: Option<Option<Utf8PathBuf>>,
+
+    /// Overwrite the recipe and image if they already exist
+    #[arg(short, long, requires = "output")]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: ImportArgs) -> Result<()> {
+    let config = &ctx.config().import;
+    let kind = match args.provider {
+        Some(kind) => kind,
+        None if args.use_claude => ProviderKind::Anthropic,
+        None => config_format(config.provider.as_deref())?.unwrap_or(ProviderKind::Openai),
+    };
+    let options = ProviderOptions {
+        model: args.model.or_else(|| config.model.clone()),
+        base_url: args.base_url.or_else(|| config.base_url.clone()),
+    };
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    info!("Fetching recipe from: {}", args.source);
+    let (recipe, metadata) = runtime.block_on(load(&args.source)).map_err(|e| {
+        warn!("Fetch failed: {:#}", e);
+        e.context("Failed to fetch recipe")
+    })?;
+    info!("Successfully fetched recipe: {}", recipe.name);
+
+    // before converting, so nothing is spent on a recipe that can't be saved
+    let destination = args
+        .output
+        .map(|dir| {
+            let dir = ctx.base_path().join(dir.unwrap_or_default());
+            save::Destination::new(&dir, &recipe, args.force)
+        })
+        .transpose()?;
+
+    let parser = ctx.parser()?;
+    let (converted, validation) = if args.skip_conversion {
+        let converted = rules::convert(&recipe);
+        let validation = Validation::check(parser, &converted, &recipe);
+        (converted, validation)
+    } else {
+        let provider = provider::build(kind, options)?;
+        runtime.block_on(convert(&recipe, provider.as_ref(), parser, args.retries))?
+    };
+
+    let stderr = std::io::stderr();
+    let color = anstream::AutoStream::choice(&stderr) != anstream::ColorChoice::Never;
+    validation.report.eprint(&recipe.name, &converted, color)?;
+    if !validation.missing.is_empty() {
+        warn!(
+            "Ingredients not found in the converted recipe:\n  {}",
+            validation.missing.join("\n  ")
+        );
+    }
+
+    match destination {
+        Some(destination) => {
+            let frontmatter = metadata.to_yaml();
+            let path = runtime.block_on(destination.save(&frontmatter, &converted))?;
+            info!("Saved recipe to {path}");
+        }
+        None => println!("{}", converted),
+    }
+    if validation.has_errors() {
+        bail!("The converted recipe has errors, fix them manually or try --retries");
+    }
+    Ok(())
+}
+
+/// Converts the recipe with a provider, retrying while it has errors
+async fn convert(
+    recipe: &Recipe,
+    provider: &dyn Provider,
+    parser: &CooklangParser,
+    retries: u32,
+) -> Result<(String, Validation)> {
+    info!("Converting recipe with {}", provider.name());
+    let prompt = prompt(recipe);
+    let mut converted = provider.complete(&prompt).await?;
+    let mut validation = Validation::check(parser, &converted, recipe);
+    for attempt in 1..=retries {
+        if !validation.has_errors() {
+            break;
+        }
+        warn!("Converted recipe is not valid, retrying ({attempt}/{retries})");
+        let repair = REPAIR_PROMPT
+            .replace("{converted}", &converted)
+            .replace("{errors}", &validation.errors());
+        converted = provider.complete(&(prompt.clone() + &repair)).await?;
+        validation = Validation::check(parser, &converted, recipe);
+    }
+    info!("Conversion done");
+    Ok((converted, validation))
+}
+
+/// Reads the recipe, from the network only if the source is a URL
+async fn load(source: &str) -> Result<(Recipe, Metadata)> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let body = reqwest::Client::new()
+            .get(source)
+            .header(USER_AGENT, BROWSER_USER_AGENT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let (recipe, mut metadata) = extract(Some(source), &body)?;
+        metadata.source = Some(source.to_string());
+        return Ok((recipe, metadata));
+    }
+
+    let content = if source == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {source}"))?
+    };
+    extract(None, &content)
+}
+
+/// Extracts the recipe from a page or JSON-LD, with the metadata
+/// `cooklang-import` leaves out
+fn extract(url: Option<&str>, content: &str) -> Result<(Recipe, Metadata)> {
+    if content.trim_start().starts_with(['{', '[']) {
+        let json: Value = serde_json::from_str(content).context("Invalid JSON-LD")?;
+        let recipe = schema::find_in_json(&json).context("No schema.org Recipe in the JSON-LD")?;
+        let metadata = Metadata::from_schema(recipe);
+        let recipe = schema::to_recipe(recipe).context("The recipe has no name")?;
+        return Ok((recipe, metadata));
+    }
+
+    let document = Html::parse_document(content);
+    if let Some(json) = schema::find_in_html(&document) {
+        if let Some(recipe) = schema::to_recipe(&json) {
+            return Ok((recipe, Metadata::from_schema(&json)));
+        }
+    }
+
+    // the JSON-LD extractor copes with some broken JSON, and pages without
+    // any schema need an LLM to find the recipe in the text
+    let context = ParsingContext {
+        url: url.unwrap_or_default().to_string(),
+        document,
+        texts: None,
+    };
+    let mut extractors: Vec<&dyn Extractor> = vec![&JsonLdExtractor];
+    if url.is_some() {
+        extractors.push(&PlainTextLlmExtractor);
+    }
+    let mut last_error = None;
+    for extractor in extractors {
+        match extractor.parse(&context) {
+            Ok(recipe) => return Ok((recipe, Metadata::default())),
+            Err(e) => {
+                tracing::debug!("Extractor failed: {e}");
+                last_error = Some(e.to_string());
+            }
+        }
+    }
+    let error = last_error.unwrap_or_else(|| "No extractor could parse the recipe".into());
+    Err(anyhow::anyhow!(error)).context("No recipe found in the page")
+}
+
+fn prompt(recipe: &Recipe) -> String {
+    PROMPT
+        .replace("{name}", &recipe.name)
+        .replace("{ingredients}", &recipe.ingredients)
+        .replace("{instructions}", &recipe.instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe() -> Recipe {
+        Recipe {
+            name: "Pancakes".to_string(),
+            description: None,
+            image: Vec::new(),
+            ingredients: "2 eggs\n250 ml milk".to_string(),
+            instructions: "Whisk the eggs with the milk.\n\nCook in a hot pan.".to_string(),
+        }
+    }
+
+    #[test]
+    fn prompt_has_the_recipe() {
+        let prompt = prompt(&recipe());
+        assert!(prompt.contains("Recipe Name: Pancakes"));
+        assert!(prompt.contains("2 eggs\n250 ml milk"));
+        assert!(!prompt.contains("{instructions}"));
+    }
+
+    #[tokio::test]
+    async fn stub_round_trip() {
+        let recipe = recipe();
+        let provider = provider::build(ProviderKind::Stub, ProviderOptions::default()).unwrap();
+        let parser = CooklangParser::canonical();
+
+        let (converted, validation) = convert(&recipe, provider.as_ref(), &parser, 2)
+            .await
+            .unwrap();
+        assert_eq!(converted, recipe.instructions);
+        assert!(!validation.has_errors());
+        // the stub doesn't mark any ingredient
+        assert_eq!(validation.missing, ["2 eggs", "250 ml milk"]);
+    }
+
+    /// Gives the answers in order, keeping the prompts it got
+    struct Scripted {
+        answers: std::sync::Mutex<Vec<&'static str>>,
+        prompts: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Scripted {
+        fn new(answers: &[&'static str]) -> Self {
+            Self {
+                answers: std::sync::Mutex::new(answers.iter().rev().copied().collect()),
+                prompts: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for Scripted {
+        fn name(&self) -> String {
+            "scripted".to_string()
+        }
+
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            let answer = self.answers.lock().unwrap().pop();
+            answer.map(String::from).context("no more answers")
+        }
+    }
+
+    const BROKEN: &str = "Whisk the @eggs{2} with the @milk{250%ml}.\n\nCook for ~{}.";
+    const FIXED: &str = "Whisk the @eggs{2} with the @milk{250%ml}.\n\nCook in a hot pan.";
+
+    #[tokio::test]
+    async fn retry_repairs() {
+        let provider = Scripted::new(&[BROKEN, FIXED]);
+        let parser = CooklangParser::canonical();
+
+        let (converted, validation) = convert(&recipe(), &provider, &parser, 2).await.unwrap();
+        assert_eq!(converted, FIXED);
+        assert!(!validation.has_errors());
+        assert!(validation.missing.is_empty());
+
+        // asked once more with the broken answer and its errors
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].starts_with(&prompts[0]));
+        assert!(prompts[1].contains(BROKEN));
+        assert!(prompts[1].contains("- Invalid timer: neither quantity nor name"));
+    }
+
+    #[tokio::test]
+    async fn retries_run_out() {
+        let provider = Scripted::new(&[BROKEN, BROKEN]);
+        let parser = CooklangParser::canonical();
+
+        let (converted, validation) = convert(&recipe(), &provider, &parser, 1).await.unwrap();
+        assert_eq!(converted, BROKEN);
+        assert!(validation.has_errors());
+        assert_eq!(provider.prompts.lock().unwrap().len(), 2);
+
+        // without retries the first answer is kept
+        let provider = Scripted::new(&[BROKEN]);
+        let (_, validation) = convert(&recipe(), &provider, &parser, 0).await.unwrap();
+        assert!(validation.has_errors());
+    }
+}
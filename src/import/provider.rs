@@ -0,0 +1,189 @@
+//! LLMs used to convert imported recipes to Cooklang
+
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use tracing::debug;
+
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_MODEL: &str = "claude-sonnet-4-20250514";
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "gpt-3.5-turbo";
+const MAX_TOKENS: u32 = 4096;
+
+/// Completes a prompt with a model
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Name for messages, like `OpenAI (gpt-4o)`
+    fn name(&self) -> String;
+
+    async fn complete(&self, prompt: &str) -> Result<String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ProviderKind {
+    /// OpenAI or any compatible API, like a local Ollama or llama.cpp server
+    #[value(alias("open-ai"))]
+    Openai,
+    /// Anthropic's Claude
+    #[value(alias("claude"))]
+    Anthropic,
+    /// Deterministic output without any model, for testing
+    Stub,
+}
+
+/// Options to build a provider, none are required
+#[derive(Debug, Default)]
+pub struct ProviderOptions {
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+}
+
+pub fn build(kind: ProviderKind, options: ProviderOptions) -> Result<Box<dyn Provider>> {
+    let provider: Box<dyn Provider> = match kind {
+        ProviderKind::Openai => {
+            let base_url = options.base_url.unwrap_or_else(|| OPENAI_URL.to_string());
+            let api_key = std::env::var("OPENAI_API_KEY").ok();
+            // local servers don't need a key
+            if api_key.is_none() && base_url == OPENAI_URL {
+                bail!("OPENAI_API_KEY must be set in the environment");
+            }
+            Box::new(OpenAi {
+                client: reqwest::Client::new(),
+                base_url,
+                api_key,
+                model: options
+                    .model
+                    .or_else(|| std::env::var("OPENAI_MODEL").ok())
+                    .unwrap_or_else(|| OPENAI_MODEL.to_string()),
+            })
+        }
+        ProviderKind::Anthropic => Box::new(Anthropic {
+            client: reqwest::Client::new(),
+            base_url: options
+                .base_url
+                .unwrap_or_else(|| ANTHROPIC_URL.to_string()),
+            api_key: std::env::var("ANTHROPIC_API_KEY")
+                .context("ANTHROPIC_API_KEY must be set in the environment")?,
+            model: options.model.unwrap_or_else(|| ANTHROPIC_MODEL.to_string()),
+        }),
+        ProviderKind::Stub => Box::new(Stub),
+    };
+    Ok(provider)
+}
+
+struct OpenAi {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+#[async_trait]
+impl Provider for OpenAi {
+    fn name(&self) -> String {
+        format!(
+            "OpenAI compatible API at {} ({})",
+            self.base_url, self.model
+        )
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let mut request = self.client.post(&url).json(&serde_json::json!({
+            "model": self.model,
+            "messages": [
+                {"role": "user", "content": prompt}
+            ],
+            "max_tokens": MAX_TOKENS
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = send(request, "OpenAI").await?;
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(String::from)
+            .context("Failed to extract content from OpenAI response")
+    }
+}
+
+struct Anthropic {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+#[async_trait]
+impl Provider for Anthropic {
+    fn name(&self) -> String {
+        format!("Anthropic ({})", self.model)
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let url = format!("{}/messages", self.base_url.trim_end_matches('/'));
+        let request = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&serde_json::json!({
+                "model": self.model,
+                "max_tokens": MAX_TOKENS,
+                "messages": [
+                    {"role": "user", "content": prompt}
+                ]
+            }));
+
+        let response = send(request, "Claude").await?;
+        response["content"][0]["text"]
+            .as_str()
+            .map(String::from)
+            .context("Failed to extract content from Claude response")
+    }
+}
+
+/// Returns the instructions of the prompt as they are
+///
+/// That's a valid recipe, without any markup, and always the same for the
+/// same recipe.
+struct Stub;
+
+#[async_trait]
+impl Provider for Stub {
+    fn name(&self) -> String {
+        "stub".to_string()
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let instructions = prompt
+            .rsplit_once("\nInstructions:\n")
+            .map_or(prompt, |(_, instructions)| instructions);
+        Ok(instructions.trim().to_string())
+    }
+}
+
+async fn send(request: reqwest::RequestBuilder, api: &str) -> Result<serde_json::Value> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("{api} API request failed"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Failed to get error response".to_string());
+        bail!("{api} API failed with status {status}: {error_text}");
+    }
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {api} response"))?;
+    debug!("{api} response: {json}");
+    Ok(json)
+}
@@ -0,0 +1,508 @@
+//! Conversion to Cooklang with simple rules, without any model
+//!
+//! Ingredient lines like `2 cups flour, sifted` are split in quantity, unit,
+//! name and preparation, and the first mention of each ingredient in the
+//! instructions is marked with all of it.
+
+use cooklang_import::model::Recipe;
+
+/// Units recognized after the quantity, in lowercase and without a final `.`
+const UNITS: &[&str] = &[
+    "g",
+    "gram",
+    "grams",
+    "gr",
+    "kg",
+    "kilogram",
+    "kilograms",
+    "mg",
+    "ml",
+    "milliliter",
+    "milliliters",
+    "millilitre",
+    "millilitres",
+    "cl",
+    "dl",
+    "l",
+    "liter",
+    "liters",
+    "litre",
+    "litres",
+    "tsp",
+    "teaspoon",
+    "teaspoons",
+    "tbsp",
+    "tbs",
+    "tbl",
+    "tablespoon",
+    "tablespoons",
+    "cup",
+    "cups",
+    "c",
+    "oz",
+    "ounce",
+    "ounces",
+    "fl oz",
+    "lb",
+    "lbs",
+    "pound",
+    "pounds",
+    "pt",
+    "pint",
+    "pints",
+    "qt",
+    "quart",
+    "quarts",
+    "gal",
+    "gallon",
+    "gallons",
+    "pinch",
+    "pinches",
+    "dash",
+    "dashes",
+    "clove",
+    "cloves",
+    "can",
+    "cans",
+    "slice",
+    "slices",
+    "stick",
+    "sticks",
+    "sprig",
+    "sprigs",
+    "bunch",
+    "bunches",
+    "handful",
+    "handfuls",
+    "package",
+    "packages",
+    "pkg",
+    "piece",
+    "pieces",
+    "head",
+    "heads",
+    "drop",
+    "drops",
+    "jar",
+    "jars",
+];
+
+const FRACTIONS: &[(char, &str)] = &[
+    ('½', "1/2"),
+    ('⅓', "1/3"),
+    ('⅔', "2/3"),
+    ('¼', "1/4"),
+    ('¾', "3/4"),
+    ('⅕', "1/5"),
+    ('⅛', "1/8"),
+];
+
+/// An ingredient line split in its parts
+#[derive(Debug)]
+pub(super) struct Ingredient {
+    quantity: Option<String>,
+    unit: Option<String>,
+    pub(super) name: String,
+    preparation: Option<String>,
+}
+
+impl Ingredient {
+    pub(super) fn parse(line: &str) -> Option<Self> {
+        let line = expand_fractions(line);
+        let (line, notes) = take_parentheses(&line);
+        let mut rest = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+
+        let quantity = take_quantity(&mut rest);
+        let unit = quantity.as_ref().and_then(|_| take_unit(&mut rest));
+        if unit.is_some() {
+            rest = rest.strip_prefix("of ").unwrap_or(rest).trim_start();
+        }
+
+        let (name, preparation) = match rest.split_once(',') {
+            Some((name, prep)) => (name, Some(prep.trim())),
+            None => match rest.strip_suffix(" to taste") {
+                Some(name) => (name, Some("to taste")),
+                None => (rest, None),
+            },
+        };
+        let name = clean(name);
+        if name.is_empty() {
+            return None;
+        }
+        let preparation = preparation
+            .into_iter()
+            .chain(notes.iter().map(String::as_str))
+            .map(clean)
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+
+        Some(Self {
+            quantity,
+            unit,
+            name,
+            preparation: (!preparation.is_empty()).then(|| preparation.join(", ")),
+        })
+    }
+
+    /// The ingredient in Cooklang with `text` as its name
+    fn to_cooklang(&self, text: &str) -> String {
+        let amount = match (&self.quantity, &self.unit) {
+            (Some(q), Some(u)) => format!("{q}%{u}"),
+            (Some(q), None) => q.clone(),
+            _ => String::new(),
+        };
+        let mut s = format!("@{text}{{{amount}}}");
+        if let Some(prep) = &self.preparation {
+            s += &format!("({prep})");
+        }
+        s
+    }
+
+    /// Words to look for in the instructions, with a lower rank for the
+    /// better ones
+    ///
+    /// The name and its endings come first, like `pepper flakes` for `red
+    /// pepper flakes`, then any other word of it.
+    fn candidates(&self) -> Vec<(u8, String)> {
+        let name = self.name.to_ascii_lowercase();
+        let words = name.split_whitespace().collect::<Vec<_>>();
+        let mut candidates = Vec::new();
+        for start in 0..words.len() {
+            let ending = words[start..].join(" ");
+            // `eggs` in the list and `egg` in the text, or the other way
+            let other = match ending.strip_suffix('s') {
+                Some(singular) if singular.len() > 2 => singular.to_string(),
+                _ => format!("{ending}s"),
+            };
+            candidates.push((0, ending));
+            candidates.push((0, other));
+        }
+        for word in &words[..words.len().saturating_sub(1)] {
+            if word.len() > 3 {
+                candidates.push((1, word.to_string()));
+            }
+        }
+        candidates
+    }
+}
+
+/// Converts the recipe to Cooklang, each instruction line is a step
+pub fn convert(recipe: &Recipe) -> String {
+    let ingredients = recipe
+        .ingredients
+        .lines()
+        .filter_map(Ingredient::parse)
+        .collect::<Vec<_>>();
+    let instructions = recipe
+        .instructions
+        .lines()
+        .map(|l| escape(l.trim()))
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    // longer matches first, so `olive oil` is not taken by `oil`
+    let mut candidates = ingredients
+        .iter()
+        .enumerate()
+        .flat_map(|(i, ingredient)| {
+            ingredient
+                .candidates()
+                .into_iter()
+                .map(move |(rank, word)| (rank, word, i))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(rank, word, _)| (*rank, std::cmp::Reverse(word.len())));
+
+    // ASCII only, so the positions are the same in both
+    let lower = instructions.to_ascii_lowercase();
+    let mut marks: Vec<(usize, usize, usize)> = Vec::new();
+    for (_, word, i) in candidates {
+        if marks.iter().any(|&(_, _, marked)| marked == i) {
+            continue;
+        }
+        let found = find_words(&lower, &word)
+            .find(|&(start, end)| marks.iter().all(|&(s, e, _)| end <= s || start >= e));
+        if let Some((start, end)) = found {
+            marks.push((start, end, i));
+        }
+    }
+    let unmatched = (0..ingredients.len())
+        .filter(|i| marks.iter().all(|&(_, _, marked)| marked != *i))
+        .collect::<Vec<_>>();
+
+    // replace from the end so the positions stay valid
+    marks.sort_by_key(|&(start, _, _)| std::cmp::Reverse(start));
+    let mut text = instructions;
+    for (start, end, i) in marks {
+        let marked = ingredients[i].to_cooklang(&text[start..end]);
+        text.replace_range(start..end, &marked);
+    }
+
+    // ingredients not in the instructions still have to be in the recipe
+    if !unmatched.is_empty() {
+        let list = unmatched
+            .into_iter()
+            .map(|i| ingredients[i].to_cooklang(&ingredients[i].name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        text = format!("{list}\n\n{text}");
+    }
+    text
+}
+
+/// `1½` to `1 1/2`
+fn expand_fractions(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match FRACTIONS.iter().find(|(f, _)| *f == c) {
+            Some((_, fraction)) => {
+                if out.ends_with(|c: char| c.is_ascii_digit()) {
+                    out.push(' ');
+                }
+                out += fraction;
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// Removes the `(...)` of the line, returning them apart
+fn take_parentheses(s: &str) -> (String, Vec<String>) {
+    let mut line = String::new();
+    let mut notes = Vec::new();
+    let mut depth = 0;
+    let mut note = String::new();
+    for c in s.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    notes.push(std::mem::take(&mut note));
+                }
+            }
+            c if depth > 0 => note.push(c),
+            c => line.push(c),
+        }
+    }
+    (line, notes)
+}
+
+/// Takes a number, fraction, mixed number or range from the start
+fn take_quantity(rest: &mut &str) -> Option<String> {
+    let is_number = |w: &str| {
+        let mut parts = w.split('/');
+        let valid = |p: Option<&str>| p.is_some_and(|p| p.parse::<f64>().is_ok());
+        match (parts.next(), parts.next(), parts.next()) {
+            (n, None, _) => valid(n),
+            (n, d, None) => valid(n) && valid(d) && !w.contains('.'),
+            _ => false,
+        }
+    };
+
+    let mut words = Vec::new();
+    let mut consumed = 0;
+    for word in rest.split_inclusive(' ') {
+        let w = word.trim();
+        if w.is_empty() {
+            consumed += word.len();
+            continue;
+        }
+        // `250g` or `2-3`
+        let split = w
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+            .unwrap_or(w.len());
+        let (number, suffix) = w.split_at(split);
+        if words.is_empty() && is_number(number) {
+            if let Some((a, b)) = suffix
+                .strip_prefix('-')
+                .or_else(|| suffix.strip_prefix('–'))
+                .and_then(|r| Some((number, r)).filter(|(_, r)| is_number(r)))
+            {
+                words.push(format!("{a}-{b}"));
+                consumed += word.len();
+                break;
+            }
+            words.push(number.to_string());
+            if suffix.is_empty() {
+                consumed += word.len();
+                continue;
+            }
+            // the unit is stuck to the number
+            consumed += split;
+            break;
+        }
+        // `1 1/2`
+        if words.len() == 1 && w.contains('/') && is_number(w) {
+            words.push(w.to_string());
+            consumed += word.len();
+        }
+        break;
+    }
+    if words.is_empty() {
+        return None;
+    }
+    *rest = &rest[consumed..];
+    Some(words.join(" "))
+}
+
+/// Takes a known unit from the start
+fn take_unit(rest: &mut &str) -> Option<String> {
+    let lower = rest.to_ascii_lowercase();
+    let len = UNITS
+        .iter()
+        .filter_map(|unit| {
+            let after = lower.strip_prefix(unit)?;
+            let after = after.strip_prefix('.').unwrap_or(after);
+            // a whole word, not the `c` of `carrots`
+            (after.is_empty() || after.starts_with(' ')).then(|| lower.len() - after.len())
+        })
+        .max()?;
+    let unit = rest[..len].trim_end_matches('.').to_string();
+    *rest = rest[len..].trim_start();
+    Some(unit)
+}
+
+/// Removes what would break the `@name{}(prep)` syntax
+fn clean(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, '@' | '#' | '~' | '{' | '}' | '(' | ')' | '%'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Instructions are plain text, their markers must not start Cooklang ones
+fn escape(s: &str) -> String {
+    s.trim_start_matches(['=', '>'])
+        .trim_start()
+        .replace(['@', '#', '~'], " ")
+        .replace("--", "-")
+        .replace("[-", "[ -")
+}
+
+/// Ranges of whole word matches of `word` in `text`
+pub(super) fn find_words<'a>(
+    text: &'a str,
+    word: &'a str,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    text.match_indices(word)
+        .map(|(start, m)| (start, start + m.len()))
+        .filter(|&(start, end)| {
+            let before = text[..start].chars().next_back();
+            let after = text[end..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::CooklangParser;
+
+    use super::*;
+
+    fn parts(line: &str) -> (Option<String>, Option<String>, String, Option<String>) {
+        let i = Ingredient::parse(line).unwrap();
+        (i.quantity, i.unit, i.name, i.preparation)
+    }
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn ingredient_lines() {
+        assert_eq!(
+            parts("1½ cups flour, sifted"),
+            (some("1 1/2"), some("cups"), "flour".into(), some("sifted"))
+        );
+        assert_eq!(parts("2-3 eggs"), (some("2-3"), None, "eggs".into(), None));
+        assert_eq!(
+            parts("250g milk"),
+            (some("250"), some("g"), "milk".into(), None)
+        );
+        assert_eq!(
+            parts("2 (14 oz) cans tomatoes"),
+            (some("2"), some("cans"), "tomatoes".into(), some("14 oz"))
+        );
+        assert_eq!(
+            parts("Salt to taste"),
+            (None, None, "Salt".into(), some("to taste"))
+        );
+        assert_eq!(
+            parts("- 1 tbsp. of olive oil"),
+            (some("1"), some("tbsp"), "olive oil".into(), None)
+        );
+        // `c` is a unit only as a whole word
+        assert_eq!(
+            parts("3 carrots"),
+            (some("3"), None, "carrots".into(), None)
+        );
+        assert!(Ingredient::parse("  ").is_none());
+        assert!(Ingredient::parse("2 cups").is_none());
+    }
+
+    #[test]
+    fn to_cooklang() {
+        let flour = Ingredient::parse("1½ cups flour, sifted").unwrap();
+        assert_eq!(flour.to_cooklang("Flour"), "@Flour{1 1/2%cups}(sifted)");
+        let eggs = Ingredient::parse("2-3 eggs").unwrap();
+        assert_eq!(eggs.to_cooklang("eggs"), "@eggs{2-3}");
+        let salt = Ingredient::parse("salt").unwrap();
+        assert_eq!(salt.to_cooklang("salt"), "@salt{}");
+    }
+
+    fn recipe(ingredients: &str, instructions: &str) -> Recipe {
+        Recipe {
+            name: "Test".to_string(),
+            description: None,
+            image: Vec::new(),
+            ingredients: ingredients.to_string(),
+            instructions: instructions.to_string(),
+        }
+    }
+
+    #[test]
+    fn marks_first_mentions() {
+        let converted = convert(&recipe(
+            "2 tbsp olive oil\n1 tsp oil\n3 eggs\n1 pinch red pepper flakes\nSalt to taste",
+            "Heat the olive oil and the oil.\nBeat the egg, add the Pepper flakes.\nServe.",
+        ));
+        assert_eq!(
+            converted,
+            "@Salt{}(to taste)\n\n\
+             Heat the @olive oil{2%tbsp} and the @oil{1%tsp}.\n\n\
+             Beat the @egg{3}, add the @Pepper flakes{1%pinch}.\n\n\
+             Serve."
+        );
+    }
+
+    #[test]
+    fn output_parses() {
+        let parser = CooklangParser::canonical();
+        let recipes = [
+            recipe(
+                "1½ cups flour, sifted\n2-3 eggs\n250g milk\n2 (14 oz) cans tomatoes\nSalt to taste",
+                "Mix the flour with the eggs and milk.\nAdd the tomatoes.\nSeason with salt.",
+            ),
+            recipe(
+                "100 g butter {soft}\n1 cup sugar @ 50%\n#2 onions\n1/2 tsp ~vanilla",
+                "== Dough ==\n> Cream the butter with the sugar -- slowly.\n\
+                 Fry the onions [- not too long -] with @home vanilla #1.\n~2 minutes.",
+            ),
+            recipe("", "Nothing to mark."),
+            recipe("3 eggs", ""),
+        ];
+        for recipe in recipes {
+            let converted = convert(&recipe);
+            let (parsed, report) = parser.parse(&converted).into_tuple();
+            assert!(!report.has_errors(), "{converted}\n{report:?}");
+            let parsed = parsed.unwrap();
+            let lines = recipe.ingredients.lines().filter(|l| !l.trim().is_empty());
+            assert_eq!(parsed.ingredients.len(), lines.count(), "{converted}");
+        }
+    }
+}
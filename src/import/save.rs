@@ -0,0 +1,113 @@
+//! Writing imported recipes into the collection
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang_import::model::Recipe;
+use tracing::{info, warn};
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Where an imported recipe and its image go
+pub struct Destination {
+    dir: Utf8PathBuf,
+    path: Utf8PathBuf,
+    /// URL and path of the image, if the recipe has one
+    image: Option<(String, Utf8PathBuf)>,
+}
+
+impl Destination {
+    /// `<Recipe Name>.cook` in `dir`, with the image of the recipe next to it
+    ///
+    /// Fails if any of them exists and `force` is not set, so it can be
+    /// checked before converting the recipe.
+    pub fn new(dir: &Utf8Path, recipe: &Recipe, force: bool) -> Result<Self> {
+        let name = file_name(&recipe.name);
+        let path = dir.join(format!("{name}.cook"));
+        let image = recipe.image.first().map(|url| {
+            let image_path = dir.join(format!("{name}.{}", image_extension(url)));
+            (url.clone(), image_path)
+        });
+
+        if !force {
+            for existing in std::iter::once(&path).chain(image.as_ref().map(|(_, p)| p)) {
+                if existing.exists() {
+                    bail!("{existing} already exists, use --force to overwrite it");
+                }
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            path,
+            image,
+        })
+    }
+
+    /// Writes the recipe and downloads the image
+    ///
+    /// Returns the path of the recipe.
+    pub async fn save(self, frontmatter: &serde_yaml::Mapping, text: &str) -> Result<Utf8PathBuf> {
+        let Self { dir, path, image } = self;
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {dir}"))?;
+
+        let mut content = String::new();
+        if !frontmatter.is_empty() {
+            content += "---\n";
+            content += &serde_yaml::to_string(frontmatter)?;
+            content += "---\n\n";
+        }
+        content += text.trim();
+        content += "\n";
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write recipe: {path}"))?;
+
+        if let Some((url, image_path)) = image {
+            // the recipe is already there, a missing image is not worth failing
+            match download(&url, &image_path).await {
+                Ok(()) => info!("Saved image to {image_path}"),
+                Err(e) => warn!("Failed to download image {url}: {e:#}"),
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+async fn download(url: &str, path: &Utf8Path) -> Result<()> {
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write image: {path}"))?;
+    Ok(())
+}
+
+/// The recipe name without the characters file systems don't like
+fn file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect::<String>();
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() {
+        "Imported recipe".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Extension of the image in the URL, `jpg` if it's not a known one
+fn image_extension(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    IMAGE_EXTENSIONS
+        .iter()
+        .find(|&&e| e == extension)
+        .copied()
+        .unwrap_or("jpg")
+}
@@ -0,0 +1,284 @@
+//! schema.org `Recipe`s in JSON-LD and microdata, without any network
+//!
+//! Both are read into the same JSON value, so microdata can be used as if
+//! it was JSON-LD.
+
+use cooklang_import::model::Recipe;
+use html_escape::decode_html_entities;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+
+/// The first recipe of the page, in JSON-LD or microdata
+pub fn find_in_html(document: &Html) -> Option<Value> {
+    let json_ld = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let from_json_ld = document
+        .select(&json_ld)
+        // the text as it is, `inner_html` would escape its `&`s
+        .map(|script| script.text().collect::<String>())
+        .filter_map(|text| serde_json::from_str::<Value>(&text).ok())
+        .find_map(|json| find_in_json(&json).cloned());
+    if from_json_ld.is_some() {
+        return from_json_ld;
+    }
+
+    let microdata = Selector::parse("[itemscope][itemtype]").unwrap();
+    document
+        .select(&microdata)
+        .find(|el| is_recipe_type(el.value().attr("itemtype").unwrap_or_default()))
+        .map(item)
+}
+
+/// Looks for the recipe in arrays and `@graph`s
+pub fn find_in_json(json: &Value) -> Option<&Value> {
+    match json {
+        Value::Array(items) => items.iter().find_map(find_in_json),
+        Value::Object(obj) => {
+            if strings(&json["@type"]).any(|t| is_recipe_type(&t)) {
+                Some(json)
+            } else {
+                obj.get("@graph").and_then(find_in_json)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The recipe as `cooklang-import` has it, `None` if it has no name
+pub fn to_recipe(recipe: &Value) -> Option<Recipe> {
+    let name = text(recipe["name"].as_str()?);
+    let description = match &recipe["description"] {
+        Value::Object(obj) => obj.get("text").and_then(Value::as_str),
+        other => other.as_str(),
+    }
+    .map(text);
+    let ingredients = match &recipe["recipeIngredient"] {
+        Value::Null => &recipe["ingredients"],
+        ingredients => ingredients,
+    };
+
+    Some(Recipe {
+        name,
+        description,
+        image: images(&recipe["image"]),
+        ingredients: strings(ingredients)
+            .map(|i| text(&i))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        instructions: steps(&recipe["recipeInstructions"]).join("\n"),
+    })
+}
+
+/// A string, a number or a list of them
+pub fn strings(value: &Value) -> Box<dyn Iterator<Item = String> + '_> {
+    match value {
+        Value::String(s) => Box::new(std::iter::once(s.clone())),
+        Value::Number(n) => Box::new(std::iter::once(n.to_string())),
+        Value::Array(items) => Box::new(items.iter().flat_map(strings)),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+fn is_recipe_type(t: &str) -> bool {
+    t.trim_end_matches('/').rsplit('/').next() == Some("Recipe")
+}
+
+/// Strings, `ImageObject`s or a list of them
+fn images(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(url) => vec![text(url)],
+        Value::Object(obj) => obj
+            .get("url")
+            .and_then(Value::as_str)
+            .map(|url| vec![text(url)])
+            .unwrap_or_default(),
+        Value::Array(items) => items.iter().flat_map(images).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Text of the `HowToStep`s, also in `HowToSection`s
+fn steps(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => s.lines().map(text).filter(|s| !s.is_empty()).collect(),
+        Value::Array(items) => items.iter().flat_map(steps).collect(),
+        Value::Object(obj) => {
+            if let Some(items) = obj.get("itemListElement") {
+                return steps(items);
+            }
+            ["text", "description"]
+                .into_iter()
+                .filter_map(|key| obj.get(key)?.as_str())
+                .map(text)
+                .filter(|s| !s.is_empty())
+                .take(1)
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Pages have entities and sometimes encode them twice
+fn text(s: &str) -> String {
+    decode_html_entities(&decode_html_entities(s))
+        .trim()
+        .to_string()
+}
+
+/// An `itemscope` element as JSON-LD
+fn item(element: ElementRef) -> Value {
+    let mut obj = Map::new();
+    if let Some(t) = element.value().attr("itemtype") {
+        obj.insert("@type".into(), t.into());
+    }
+    properties(element, &mut obj);
+    Value::Object(obj)
+}
+
+/// Adds the `itemprop`s under `element` to `obj`, without going into
+/// nested items
+fn properties(element: ElementRef, obj: &mut Map<String, Value>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let el = child.value();
+        let nested = el.attr("itemscope").is_some();
+        if let Some(props) = el.attr("itemprop") {
+            let value = if nested {
+                item(child)
+            } else {
+                property_value(child)
+            };
+            for prop in props.split_whitespace() {
+                match obj.get_mut(prop) {
+                    Some(Value::Array(values)) => values.push(value.clone()),
+                    Some(existing) => {
+                        *existing = Value::Array(vec![existing.take(), value.clone()])
+                    }
+                    None => {
+                        obj.insert(prop.to_string(), value.clone());
+                    }
+                }
+            }
+        }
+        if !nested {
+            properties(child, obj);
+        }
+    }
+}
+
+fn property_value(element: ElementRef) -> Value {
+    let el = element.value();
+    let attr = match el.name() {
+        "meta" => el.attr("content"),
+        "img" | "audio" | "video" | "source" => el.attr("src"),
+        "a" | "link" | "area" => el.attr("href"),
+        "time" => el.attr("datetime"),
+        "data" | "meter" => el.attr("value"),
+        _ => el.attr("content"),
+    };
+    match attr {
+        Some(value) => value.into(),
+        None => element
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn json_ld_graph_and_arrays() {
+        let graph = json!({
+            "@context": "https://schema.org",
+            "@graph": [
+                { "@type": "WebPage", "name": "Page" },
+                { "@type": ["Recipe", "NewsArticle"], "name": "Soup" },
+            ],
+        });
+        assert_eq!(find_in_json(&graph).unwrap()["name"], "Soup");
+
+        let array = json!([
+            { "@type": "Organization", "name": "Site" },
+            { "@type": "http://schema.org/Recipe", "name": "Stew" },
+        ]);
+        assert_eq!(find_in_json(&array).unwrap()["name"], "Stew");
+
+        let nested = json!([{ "@graph": [{ "@type": "Recipe", "name": "Pie" }] }]);
+        assert_eq!(find_in_json(&nested).unwrap()["name"], "Pie");
+
+        assert!(find_in_json(&json!({ "@type": "WebPage" })).is_none());
+        assert!(find_in_json(&json!("Recipe")).is_none());
+    }
+
+    #[test]
+    fn json_ld_in_html() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">{ broken</script>
+            <script type="application/ld+json">{"@type": "Person", "name": "Me"}</script>
+            <script type="application/ld+json">
+                {"@type": "Recipe", "name": "Tomato &amp;amp; Basil Soup",
+                 "recipeIngredient": ["2 tomatoes", "basil"],
+                 "recipeInstructions": [
+                    {"@type": "HowToSection", "itemListElement": [
+                        {"@type": "HowToStep", "text": "Chop."},
+                        {"@type": "HowToStep", "text": "Boil."}
+                    ]}
+                 ],
+                 "image": {"@type": "ImageObject", "url": "https://example.com/soup.jpg"}}
+            </script>
+        </head></html>"#;
+        let json = find_in_html(&Html::parse_document(html)).unwrap();
+        let recipe = to_recipe(&json).unwrap();
+        assert_eq!(recipe.name, "Tomato & Basil Soup");
+        assert_eq!(recipe.ingredients, "2 tomatoes\nbasil");
+        assert_eq!(recipe.instructions, "Chop.\nBoil.");
+        assert_eq!(recipe.image, ["https://example.com/soup.jpg"]);
+    }
+
+    #[test]
+    fn microdata() {
+        let html = r#"<body>
+            <div itemscope itemtype="https://schema.org/Recipe">
+                <h1 itemprop="name">Pancakes</h1>
+                <img itemprop="image" src="pancakes.png">
+                <meta itemprop="prepTime" content="PT10M">
+                <ul>
+                    <li itemprop="recipeIngredient">2 eggs</li>
+                    <li itemprop="recipeIngredient">250 ml
+                        milk</li>
+                </ul>
+                <div itemprop="author" itemscope itemtype="https://schema.org/Person">
+                    <span itemprop="name">Someone</span>
+                </div>
+                <div itemprop="recipeInstructions">
+                    <p>Mix.</p>
+                </div>
+            </div>
+        </body>"#;
+        let json = find_in_html(&Html::parse_document(html)).unwrap();
+        assert_eq!(json["@type"], "https://schema.org/Recipe");
+        assert_eq!(json["name"], "Pancakes");
+        assert_eq!(json["image"], "pancakes.png");
+        assert_eq!(json["prepTime"], "PT10M");
+        assert_eq!(json["recipeIngredient"], json!(["2 eggs", "250 ml milk"]));
+        // nested items are kept apart
+        assert_eq!(json["author"]["name"], "Someone");
+
+        let recipe = to_recipe(&json).unwrap();
+        assert_eq!(recipe.instructions, "Mix.");
+    }
+
+    #[test]
+    fn no_recipe() {
+        let html = r#"<div itemscope itemtype="https://schema.org/Person">
+            <span itemprop="name">Someone</span></div>"#;
+        assert!(find_in_html(&Html::parse_document(html)).is_none());
+        assert!(to_recipe(&json!({ "@type": "Recipe" })).is_none());
+    }
+}
@@ -0,0 +1,130 @@
+//! Checks of the recipes converted by a provider
+
+use cooklang::{error::SourceReport, CooklangParser};
+use cooklang_import::model::Recipe;
+
+use super::rules::{find_words, Ingredient};
+
+/// Result of parsing a converted recipe
+pub struct Validation {
+    pub report: SourceReport,
+    /// Lines of the original ingredient list without an `@ingredient`, empty
+    /// if the recipe has errors
+    pub missing: Vec<String>,
+}
+
+impl Validation {
+    pub fn check(parser: &CooklangParser, converted: &str, source: &Recipe) -> Self {
+        let (recipe, report) = parser.parse(converted).into_tuple();
+
+        // with errors there's no recipe to look for ingredients in
+        let Some(recipe) = recipe else {
+            return Self {
+                report,
+                missing: Vec::new(),
+            };
+        };
+
+        let names = recipe
+            .ingredients
+            .iter()
+            .map(|i| i.name.to_lowercase())
+            .collect::<Vec<_>>();
+        let missing = source
+            .ingredients
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .filter(|line| {
+                let name = Ingredient::parse(line).map_or(line.to_string(), |i| i.name);
+                let name = name.to_lowercase();
+                !names.iter().any(|converted| mentions(&name, converted))
+            })
+            .map(String::from)
+            .collect();
+
+        Self { report, missing }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.report.has_errors()
+    }
+
+    /// The errors, one per line, to give back to the provider
+    pub fn errors(&self) -> String {
+        self.report
+            .errors()
+            .map(|e| format!("- {e}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// If the `name` of an ingredient line has the last word of a `converted`
+/// ingredient name, as a whole word and singular or plural
+///
+/// The original lines usually have more or less words, like `black pepper`
+/// for `@ground black pepper{}`, but the last one tends to be there. Whole
+/// words, so `salt` is not in `unsalted butter`.
+fn mentions(name: &str, converted: &str) -> bool {
+    let Some(word) = converted.split_whitespace().last() else {
+        return false;
+    };
+    let singular = ["es", "s"]
+        .iter()
+        .filter_map(|suffix| word.strip_suffix(suffix))
+        .filter(|singular| singular.len() > 2)
+        .map(String::from);
+    let plural = [format!("{word}s"), format!("{word}es")];
+    let mut forms = std::iter::once(word.to_string())
+        .chain(singular)
+        .chain(plural);
+    forms.any(|form| find_words(name, &form).next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(converted: &str, ingredients: &str) -> Validation {
+        let source = Recipe {
+            name: "Test".to_string(),
+            description: None,
+            image: Vec::new(),
+            ingredients: ingredients.to_string(),
+            instructions: String::new(),
+        };
+        Validation::check(&CooklangParser::canonical(), converted, &source)
+    }
+
+    #[test]
+    fn missing() {
+        let validation = check(
+            "Whisk @eggs{2} with @salt{} and @ground black pepper{}, add @flour{1%cup}.",
+            "2 large eggs\n1 eggplant\n\n2 tbsp unsalted butter\nsalt and pepper\n1 cup all-purpose flour, sifted",
+        );
+        assert_eq!(validation.missing, ["1 eggplant", "2 tbsp unsalted butter"]);
+
+        // singular in the list and plural in the recipe, or the other way
+        let validation = check("Add @tomato{2} and @onions{1}.", "2 tomatoes\n1 onion");
+        assert!(validation.missing.is_empty());
+
+        // no recipe to look in
+        let validation = check("Wait ~{}.", "1 egg");
+        assert!(validation.missing.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let validation = check("Wait ~{}.\n\nAnd ~{} more.", "");
+        assert!(validation.has_errors());
+        assert_eq!(
+            validation.errors(),
+            "- Invalid timer: neither quantity nor name\n- Invalid timer: neither quantity nor name"
+        );
+
+        let validation = check("Wait ~{10%min}.", "");
+        assert!(!validation.has_errors());
+        assert_eq!(validation.errors(), "");
+    }
+}
@@ -33,11 +33,14 @@ use anyhow::{bail, Context as AnyhowContext, Result};
 use args::{CliArgs, Command};
 use camino::{Utf8Path, Utf8PathBuf};
 use clap::Parser;
+use config::Config;
 use cooklang::CooklangParser;
 use once_cell::sync::OnceCell;
 
 // commands
+mod aisle;
 mod import;
+mod plan;
 mod recipe;
 mod report;
 mod search;
@@ -47,12 +50,15 @@ mod shopping_list;
 
 // other modules
 mod args;
+mod config;
 mod util;
 
 const LOCAL_CONFIG_DIR: &str = "config";
 const APP_NAME: &str = "cook";
 const UTF8_PATH_PANIC: &str = "cook only supports UTF-8 paths.";
 const AUTO_AISLE: &str = "aisle.conf";
+const AUTO_PANTRY: &str = "pantry.conf";
+const AUTO_CONFIG: &str = "cook.toml";
 
 pub fn main() -> Result<()> {
     configure_logging();
@@ -65,6 +71,8 @@ pub fn main() -> Result<()> {
         Command::Recipe(args) => recipe::run(&ctx, args),
         Command::Server(args) => server::run(ctx, args),
         Command::ShoppingList(args) => shopping_list::run(&ctx, args),
+        Command::Plan(args) => plan::run(&ctx, args),
+        Command::Aisle(args) => aisle::run(&ctx, args),
         Command::Seed(args) => seed::run(&ctx, args),
         Command::Search(args) => search::run(&ctx, args),
         Command::Import(args) => import::run(&ctx, args),
@@ -75,28 +83,56 @@ pub fn main() -> Result<()> {
 pub struct Context {
     parser: OnceCell<CooklangParser>,
     base_path: Utf8PathBuf,
+    config: Config,
 }
 
 impl Context {
     fn parser(&self) -> Result<&CooklangParser> {
-        self.parser.get_or_try_init(configure_parser)
+        self.parser
+            .get_or_try_init(|| self.config.parser.build_parser())
     }
 
     fn aisle(&self) -> Option<Utf8PathBuf> {
-        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_AISLE);
-
-        tracing::trace!("checking auto aisle file: {auto}");
+        config_file(&self.base_path, AUTO_AISLE)
+    }
 
-        auto.is_file().then_some(auto).or_else(|| {
-            let global = global_file_path(AUTO_AISLE).ok()?;
-            tracing::trace!("checking global auto aisle file: {global}");
-            global.is_file().then_some(global)
-        })
+    fn pantry(&self) -> Option<Utf8PathBuf> {
+        config_file(&self.base_path, AUTO_PANTRY)
     }
 
     fn base_path(&self) -> &Utf8PathBuf {
         &self.base_path
     }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+#[cfg(test)]
+impl Context {
+    /// The default config with recipes in `base_path`
+    fn for_tests(base_path: &Utf8Path) -> Self {
+        Self {
+            parser: OnceCell::new(),
+            base_path: base_path.to_path_buf(),
+            config: Config::default(),
+        }
+    }
+}
+
+/// Looks for a config file in the local config dir of `base_path`, falling
+/// back to the global config dir.
+fn config_file(base_path: &Utf8Path, name: &str) -> Option<Utf8PathBuf> {
+    let auto = base_path.join(LOCAL_CONFIG_DIR).join(name);
+
+    tracing::trace!("checking auto {name} file: {auto}");
+
+    auto.is_file().then_some(auto).or_else(|| {
+        let global = global_file_path(name).ok()?;
+        tracing::trace!("checking global auto {name} file: {global}");
+        global.is_file().then_some(global)
+    })
 }
 
 fn configure_context() -> Result<Context> {
@@ -117,22 +153,25 @@ fn configure_context() -> Result<Context> {
         bail!("Base path is not a directory: {}", absolute_base_path);
     }
 
+    let config = match config_file(&absolute_base_path, AUTO_CONFIG) {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+
     Ok(Context {
         parser: OnceCell::new(),
         base_path: absolute_base_path,
+        config,
     })
 }
 
-fn configure_parser() -> Result<CooklangParser> {
-    Ok(CooklangParser::canonical())
-}
-
 fn configure_logging() {
     tracing_subscriber::fmt()
         // Log this crate at level `trace`, but all other crates at level `info`.
         .with_env_filter("info,cooklang=info,cook=trace")
         .without_time()
         .with_target(false)
+        .with_writer(std::io::stderr)
         .compact()
         .init();
 }
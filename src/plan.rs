@@ -0,0 +1,89 @@
+use std::io::Write;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+use yansi::Paint;
+
+use crate::{
+    util::{
+        get_recipe,
+        plan::{Plan, PlannedRecipe},
+        Scaling,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct PlanArgs {
+    #[command(subcommand)]
+    command: PlanCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum PlanCommand {
+    /// Show a meal plan as an agenda
+    #[command(alias = "s")]
+    Show(ShowArgs),
+}
+
+#[derive(Debug, Args)]
+struct ShowArgs {
+    /// Meal plan file
+    ///
+    /// YAML with days, the meals of each day and their recipes. A recipe is
+    /// written like in the command line, `Neapolitan Pizza:2`, or as
+    /// `{ recipe: Neapolitan Pizza, servings: 4 }`.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    plan: Utf8PathBuf,
+}
+
+pub fn run(ctx: &Context, args: PlanArgs) -> Result<()> {
+    match args.command {
+        PlanCommand::Show(args) => show(ctx, args),
+    }
+}
+
+fn show(ctx: &Context, args: ShowArgs) -> Result<()> {
+    let plan = Plan::load(&args.plan)?;
+
+    let mut table = tabular::Table::new("  {:<}  {:<}");
+    for day in &plan.days {
+        table.add_heading(day.name.bold().to_string());
+        for meal in &day.meals {
+            let mut name = Some(meal.name.as_str());
+            for recipe in &meal.recipes {
+                table.add_row(
+                    tabular::Row::new()
+                        .with_ansi_cell(name.take().unwrap_or_default().cyan())
+                        .with_ansi_cell(describe(ctx, recipe)),
+                );
+            }
+            if name.is_some() {
+                table.add_row(
+                    tabular::Row::new()
+                        .with_ansi_cell(meal.name.as_str().cyan())
+                        .with_ansi_cell("–".dim()),
+                );
+            }
+        }
+    }
+
+    write!(std::io::stdout().lock(), "{table}")?;
+    Ok(())
+}
+
+fn describe(ctx: &Context, recipe: &PlannedRecipe) -> String {
+    let mut s = recipe.recipe.clone();
+    match recipe.scaling {
+        Some(Scaling::Servings(servings)) => {
+            s += &format!(", {servings} servings").dim().to_string();
+        }
+        Some(Scaling::Factor(scale)) => s += &format!(" ×{scale}").dim().to_string(),
+        None => {}
+    }
+    if get_recipe(ctx.base_path(), &recipe.recipe).is_err() {
+        s += &" (not found)".red().to_string();
+    }
+    s
+}
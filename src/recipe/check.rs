@@ -0,0 +1,201 @@
+use std::io::Write;
+
+use anstream::ColorChoice;
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+    util::{diagnostic::Diagnostic, find_recipe_files},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Recipe file or directory to check, none for the base path
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    path: Option<Utf8PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    /// Fail on warnings too
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    checked: usize,
+    errors: usize,
+    warnings: usize,
+    recipes: Vec<RecipeReport>,
+}
+
+#[derive(Serialize)]
+struct RecipeReport {
+    path: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+pub fn run(ctx: &Context, args: CheckArgs) -> Result<()> {
+    let color = anstream::AutoStream::choice(&std::io::stdout()) != ColorChoice::Never;
+    check(ctx, args, &mut anstream::stdout().lock(), color)
+}
+
+/// Writes the report to `out`, failing if the recipes have errors, or
+/// warnings with `--strict`
+fn check(ctx: &Context, args: CheckArgs, mut out: impl Write, color: bool) -> Result<()> {
+    let parser = ctx.parser()?;
+
+    let path = args.path.unwrap_or_else(|| ctx.base_path().clone());
+    let (files, base) = if path.is_dir() {
+        (find_recipe_files(&path)?, path)
+    } else {
+        (vec![path], ctx.base_path().clone())
+    };
+
+    let mut report = Report::default();
+
+    for file in &files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read recipe file: {file}"))?;
+        let relative = file.strip_prefix(&base).unwrap_or(file);
+
+        report.checked += 1;
+
+        let diagnostics = parser.parse(&content).into_report();
+        if diagnostics.is_empty() {
+            continue;
+        }
+
+        report.errors += diagnostics.errors().count();
+        report.warnings += diagnostics.warnings().count();
+
+        match args.format {
+            OutputFormat::Human => {
+                diagnostics.write(relative.as_str(), &content, color, &mut out)?
+            }
+            OutputFormat::Json => report.recipes.push(RecipeReport {
+                path: relative.to_string(),
+                diagnostics: diagnostics
+                    .iter()
+                    .map(|d| Diagnostic::new(d, &content))
+                    .collect(),
+            }),
+        }
+    }
+
+    match args.format {
+        OutputFormat::Human => writeln!(
+            out,
+            "Checked {} recipes: {} errors, {} warnings",
+            report.checked, report.errors, report.warnings
+        )?,
+        OutputFormat::Json => {
+            if args.pretty {
+                serde_json::to_writer_pretty(&mut out, &report)?;
+            } else {
+                serde_json::to_writer(&mut out, &report)?;
+            }
+            writeln!(out)?;
+        }
+    }
+
+    if report.errors > 0 || (args.strict && report.warnings > 0) {
+        bail!("Recipe check failed")
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Checks the recipes of a new base path, returning the JSON report and
+    /// if the check passed
+    fn check_json(recipes: &[(&str, &str)], strict: bool) -> (Value, bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        for (name, content) in recipes {
+            std::fs::write(base_path.join(name), content).unwrap();
+        }
+        let ctx = Context::for_tests(&base_path);
+        let args = CheckArgs {
+            path: None,
+            format: OutputFormat::Json,
+            pretty: false,
+            strict,
+        };
+
+        let mut out = Vec::new();
+        let passed = check(&ctx, args, &mut out, false).is_ok();
+        (serde_json::from_slice(&out).unwrap(), passed)
+    }
+
+    const OK: (&str, &str) = ("Ok.cook", "Mix @flour{100%g}.");
+    const WARNING: (&str, &str) = ("Warning.cook", "---\nservings: many\n---\nMix.");
+    const ERROR: (&str, &str) = ("Error.cook", "Mix.\nWait ~{}.");
+
+    #[test]
+    fn json_report() {
+        let (report, passed) = check_json(&[OK, WARNING, ERROR], false);
+        assert!(!passed);
+        assert_eq!(
+            report,
+            json!({
+                "checked": 3,
+                "errors": 1,
+                "warnings": 1,
+                "recipes": [
+                    {
+                        "path": "Error.cook",
+                        "diagnostics": [{
+                            "severity": "error",
+                            "message": "Invalid timer: neither quantity nor name",
+                            "line": 2,
+                            "column": 7,
+                            "hints": [],
+                        }],
+                    },
+                    {
+                        "path": "Warning.cook",
+                        "diagnostics": [{
+                            "severity": "warning",
+                            "message": "Unsupported value for key: 'servings'",
+                            "line": 2,
+                            "column": 1,
+                            "hints": [],
+                        }],
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn exit_status() {
+        assert!(check_json(&[OK], false).1);
+        assert!(check_json(&[OK], true).1);
+        // warnings only fail with --strict
+        assert!(check_json(&[OK, WARNING], false).1);
+        assert!(!check_json(&[OK, WARNING], true).1);
+        assert!(!check_json(&[ERROR], false).1);
+        assert!(check_json(&[], true).1);
+    }
+}
@@ -0,0 +1,443 @@
+use std::ops::Range;
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::{
+    parser::{Event, PullParser},
+    CooklangParser, Extensions, ScalableRecipe,
+};
+use tracing::warn;
+
+use crate::{
+    util::{
+        cooklang_to_cooklang::{print_cooklang_with_width, Comments},
+        find_recipe_files,
+    },
+    Context,
+};
+
+/// Line width used when wrapping steps
+const WIDTH: usize = 80;
+
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Recipe file or directory to format, none for the base path
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    path: Option<Utf8PathBuf>,
+
+    /// Don't write the files, fail if any of them is not formatted
+    #[arg(long)]
+    check: bool,
+}
+
+pub fn run(ctx: &Context, args: FmtArgs) -> Result<()> {
+    let parser = ctx.parser()?;
+
+    let path = args.path.unwrap_or_else(|| ctx.base_path().clone());
+    let (files, base) = if path.is_dir() {
+        (find_recipe_files(&path)?, path)
+    } else {
+        (vec![path], ctx.base_path().clone())
+    };
+
+    let mut unformatted = 0;
+    let mut failed = 0;
+
+    for file in &files {
+        let relative = file.strip_prefix(&base).unwrap_or(file);
+
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read recipe file: {file}"))?;
+
+        let formatted = match format_recipe(&content, parser) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                warn!("Skipping {relative}: {e:#}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        if formatted == content {
+            continue;
+        }
+        unformatted += 1;
+
+        if args.check {
+            println!("Would reformat: {relative}");
+            print_diff(&content, &formatted);
+        } else {
+            std::fs::write(file, &formatted)
+                .with_context(|| format!("Failed to write recipe file: {file}"))?;
+            println!("Formatted: {relative}");
+        }
+    }
+
+    if args.check && unformatted > 0 {
+        bail!(
+            "{unformatted} of {} recipes would be reformatted",
+            files.len()
+        )
+    }
+    if failed > 0 {
+        bail!("{failed} recipes could not be formatted")
+    }
+
+    Ok(())
+}
+
+/// Format a recipe, making sure nothing is lost on the way
+fn format_recipe(content: &str, parser: &CooklangParser) -> Result<String> {
+    let recipe = parse(content, parser)?;
+    let formatted = print(&recipe, &comments(content, &recipe, parser.extensions())?)?;
+
+    let reparsed = parse(&formatted, parser).context("formatted output does not parse")?;
+    if reparsed != recipe {
+        bail!("formatted output is not equivalent to the original recipe")
+    }
+    let comments = comments(&formatted, &reparsed, parser.extensions())?;
+    if print(&reparsed, &comments)? != formatted {
+        bail!("formatting is not stable")
+    }
+
+    Ok(formatted)
+}
+
+fn parse(content: &str, parser: &CooklangParser) -> Result<ScalableRecipe> {
+    let (recipe, report) = parser.parse(content).into_tuple();
+    match recipe {
+        Some(recipe) if !report.has_errors() => Ok(recipe),
+        _ => bail!(
+            "{}",
+            report
+                .errors()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn print(recipe: &ScalableRecipe, comments: &Comments) -> Result<String> {
+    let mut buf = Vec::new();
+    print_cooklang_with_width(recipe, WIDTH, comments, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Find the comments in a recipe and where to write them back.
+///
+/// The parser drops comments, so they are whatever is left in the source
+/// between the spans of the events it reports. A content with a comment on
+/// any of its lines is kept as it is, other comments go before the next
+/// content or section header.
+fn comments(content: &str, recipe: &ScalableRecipe, extensions: Extensions) -> Result<Comments> {
+    let mut body_start = 0;
+    let mut covered = Vec::new();
+    let mut contents: Vec<Range<usize>> = Vec::new();
+    let mut current: Option<Vec<Range<usize>>> = None;
+    // (covered up to, name position, first content after it)
+    let mut headers = Vec::new();
+
+    for event in PullParser::new(content, extensions) {
+        let spans = match event {
+            Event::YAMLFrontMatter(text) => {
+                let end = text.span().end();
+                let fence = content[end..].find("---").map_or(end, |i| end + i);
+                body_start = line_end(content, fence);
+                continue;
+            }
+            Event::Metadata { key, value } => vec![key.span().range(), value.span().range()],
+            Event::Section { name } => {
+                let covered_to = covered.iter().map(|s: &Range<usize>| s.end).max();
+                let name = name.map(|name| name.span().range());
+                headers.push((
+                    covered_to.unwrap_or(body_start),
+                    name.as_ref().map(|n| n.start),
+                    contents.len(),
+                ));
+                name.into_iter().collect()
+            }
+            Event::Start(_) => {
+                current = Some(Vec::new());
+                continue;
+            }
+            Event::End(_) => {
+                let spans = current.take().unwrap_or_default();
+                let start = spans.iter().map(|s| s.start).min();
+                let end = spans.iter().map(|s| s.end).max();
+                contents.extend(start.zip(end).map(|(start, end)| start..end));
+                continue;
+            }
+            Event::Text(text) => text
+                .fragments()
+                .iter()
+                .map(|f| f.start()..f.end())
+                .collect(),
+            Event::Ingredient(c) => vec![c.span().range()],
+            Event::Cookware(c) => vec![c.span().range()],
+            Event::Timer(c) => vec![c.span().range()],
+            _ => continue,
+        };
+        if let Some(current) = &mut current {
+            current.extend(spans.iter().cloned());
+        }
+        covered.extend(spans);
+    }
+
+    // Comments are in the gaps, where the parser reported nothing
+    covered.sort_by_key(|s| s.start);
+    let mut found = Vec::new();
+    let mut pos = body_start;
+    for span in covered
+        .iter()
+        .cloned()
+        .chain(std::iter::once(content.len()..content.len()))
+    {
+        if span.start > pos {
+            find_comments(content, pos..span.start, &mut found);
+        }
+        pos = pos.max(span.end);
+    }
+
+    if found.is_empty() {
+        return Ok(Comments::default());
+    }
+    let total: usize = recipe.sections.iter().map(|s| s.content.len()).sum();
+    if contents.len() != total {
+        bail!("could not match the recipe contents to the source")
+    }
+
+    // Unnamed headers (`====`) are not reported with a span
+    let is_commented = |p: usize| found.iter().any(|c: &Range<usize>| c.contains(&p));
+    let headers: Vec<(usize, usize)> = headers
+        .into_iter()
+        .filter_map(|(from, name, first)| {
+            let pos = name.map(|n| line_start(content, n)).or_else(|| {
+                let mut start = line_start(content, from);
+                if start < from {
+                    start = line_end(content, from);
+                }
+                content[start..]
+                    .split_inclusive('\n')
+                    .scan(start, |p, line| {
+                        let this = *p;
+                        *p += line.len();
+                        Some((this, line))
+                    })
+                    .find(|(p, line)| line.trim_start().starts_with('=') && !is_commented(*p))
+                    .map(|(p, _)| p)
+            })?;
+            Some((pos, first))
+        })
+        .collect();
+
+    let mut comments = Comments::default();
+    let mut verbatim_end = vec![None; contents.len()];
+    for comment in found {
+        let inside = contents.iter().position(|c| {
+            line_start(content, c.start) <= comment.start
+                && comment.start <= line_end(content, c.end)
+        });
+        if let Some(index) = inside {
+            let end = verbatim_end[index].get_or_insert(contents[index].end);
+            *end = (*end).max(comment.end);
+            continue;
+        }
+
+        let text = content[comment.clone()].trim_end().to_string();
+        let next = contents.iter().position(|c| c.start >= comment.end);
+        let Some(next) = next else {
+            comments.after.push(text);
+            continue;
+        };
+        let before_header = headers
+            .iter()
+            .any(|&(pos, first)| pos >= comment.end && first == next);
+        let target = if before_header {
+            &mut comments.before_header
+        } else {
+            &mut comments.before_content
+        };
+        target.entry(next).or_default().push(text);
+    }
+
+    for (index, end) in verbatim_end.into_iter().enumerate() {
+        let Some(end) = end else { continue };
+        let range = line_start(content, contents[index].start)..line_end(content, end);
+        if contents.get(index + 1).is_some_and(|c| c.start < range.end) {
+            bail!("could not keep the comments in place")
+        }
+        let source = content[range].trim_end().to_string();
+        comments.verbatim.insert(index, source);
+    }
+
+    Ok(comments)
+}
+
+/// Push the `--` line and `[- -]` block comments in `gap`
+fn find_comments(content: &str, gap: Range<usize>, found: &mut Vec<Range<usize>>) {
+    let mut pos = gap.start;
+    while pos + 1 < gap.end {
+        let rest = &content[pos..gap.end];
+        let Some(start) = [rest.find("--"), rest.find("[-")]
+            .into_iter()
+            .flatten()
+            .min()
+        else {
+            break;
+        };
+        let start = pos + start;
+        let end = if content[start..].starts_with("--") {
+            line_end(content, start)
+        } else {
+            content[start..]
+                .find("-]")
+                .map_or(content.len(), |i| start + i + 2)
+        };
+        found.push(start..end);
+        pos = end;
+    }
+}
+
+fn line_start(content: &str, pos: usize) -> usize {
+    content[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// End of the line at `pos`, without the newline
+fn line_end(content: &str, pos: usize) -> usize {
+    content[pos..].find('\n').map_or(content.len(), |i| pos + i)
+}
+
+/// Minimal line diff, good enough to spot what changed
+fn print_diff(old: &str, new: &str) {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    for line in &old[prefix..old.len() - suffix] {
+        println!("- {line}");
+    }
+    for line in &new[prefix..new.len() - suffix] {
+        println!("+ {line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPE: &str = "\
+---
+title: Bread
+time: 2--3 minutes
+---
+Mix @flour{=500%g} and @water{300%ml} in a #bowl{}.
+Knead for ~{10%min}.
+
+== Bake ==
+> Preheat the oven.
+
+Bake for ~{30%min}.
+";
+
+    const COMMENTED: &str = "\
+---
+title: Bread
+---
+Mix @flour{=500%g}   and @water{300%ml} -- by weight
+in a #bowl{}.
+-- on its own line
+Knead for ~{10%min}, [- not too long -] then rest.
+
+[- before
+the header -]
+== Bake ==
+> Preheat the oven. -- hot
+
+====
+Bake for 2--3 minutes.
+
+Let it cool.
+-- at the end
+";
+
+    fn format(content: &str) -> String {
+        format_recipe(content, &CooklangParser::canonical()).unwrap()
+    }
+
+    fn parse(content: &str) -> ScalableRecipe {
+        super::parse(content, &CooklangParser::canonical()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for recipe in [RECIPE, COMMENTED] {
+            assert_eq!(parse(&format(recipe)), parse(recipe));
+        }
+    }
+
+    #[test]
+    fn idempotent() {
+        for recipe in [RECIPE, COMMENTED] {
+            let formatted = format(recipe);
+            assert_eq!(format(&formatted), formatted);
+        }
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            format(RECIPE),
+            "\
+---
+title: Bread
+time: 2--3 minutes
+---
+
+Mix @flour{=500%g} and @water{300%ml} in a #bowl. Knead for ~{10%min}.
+
+== Bake ==
+> Preheat the oven.
+
+Bake for ~{30%min}.
+"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        assert_eq!(
+            format(COMMENTED),
+            "\
+---
+title: Bread
+---
+
+Mix @flour{=500%g}   and @water{300%ml} -- by weight
+in a #bowl{}.
+
+-- on its own line
+Knead for ~{10%min}, [- not too long -] then rest.
+
+[- before
+the header -]
+== Bake ==
+> Preheat the oven. -- hot
+
+====
+Bake for 2--3 minutes.
+
+Let it cool.
+-- at the end
+"
+        );
+    }
+}
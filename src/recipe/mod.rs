@@ -34,6 +34,8 @@ use clap::{Args, Subcommand};
 
 use crate::Context;
 
+mod check;
+mod fmt;
 mod read;
 
 #[derive(Debug, Args)]
@@ -51,6 +53,13 @@ enum RecipeCommand {
     /// Parse and print a Cooklang recipe file
     #[command(alias = "r")]
     Read(read::ReadArgs),
+
+    /// Check recipe files for errors and warnings
+    #[command(alias = "c")]
+    Check(check::CheckArgs),
+
+    /// Format recipe files in a consistent style
+    Fmt(fmt::FmtArgs),
 }
 
 pub fn run(ctx: &Context, args: RecipeArgs) -> Result<()> {
@@ -58,6 +67,8 @@ pub fn run(ctx: &Context, args: RecipeArgs) -> Result<()> {
 
     match command {
         RecipeCommand::Read(args) => read::run(ctx, args),
+        RecipeCommand::Check(args) => check::run(ctx, args),
+        RecipeCommand::Fmt(args) => fmt::run(ctx, args),
     }
 }
 
@@ -66,12 +77,21 @@ struct RecipeInputArgs {
     /// Input recipe, none for stdin
     ///
     /// This can be a full path or a partial path.
-    /// You can also specify a scale inline using `path@<scale>` (e.g., `Easy Pancakes.cook@3`).
+    /// You can also specify a scale inline using `path:<scale>` (e.g., `Easy Pancakes.cook:3`),
+    /// or a number of servings with `path:<servings>p` (e.g., `Easy Pancakes.cook:4p`).
     /// Note. `.cook` extension is optional.
     #[arg(value_hint = clap::ValueHint::FilePath)]
     recipe: Option<Utf8PathBuf>,
 
     /// Scale factor number, defaults to 1
-    #[arg(short, long, default_value_t = 1.0)]
-    scale: f64,
+    ///
+    /// The default can be changed with `recipe.scale` in the config file.
+    #[arg(short, long)]
+    scale: Option<f64>,
+
+    /// Scale to this number of servings
+    ///
+    /// The recipe must have `servings` in its metadata.
+    #[arg(long, conflicts_with = "scale")]
+    servings: Option<u32>,
 }
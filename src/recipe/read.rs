@@ -37,7 +37,10 @@ use camino::Utf8PathBuf;
 use cooklang_find::RecipeEntry;
 
 use crate::{
-    util::{split_recipe_name_and_scaling_factor, write_to_output},
+    util::{
+        config_format, parse_recipe, split_recipe_name_and_scaling_factor, units::Units,
+        write_to_output, Scaling,
+    },
     Context,
 };
 
@@ -52,13 +55,18 @@ pub struct ReadArgs {
 
     /// Output format
     ///
-    /// Tries to infer it from output file extension. Defaults to "human".
+    /// Tries to infer it from output file extension. Defaults to
+    /// `recipe.format` from the config file or "human".
     #[arg(short, long, value_enum)]
     format: Option<OutputFormat>,
 
     /// Pretty output format, if available
     #[arg(long)]
     pretty: bool,
+
+    /// Convert quantities to this unit system
+    #[arg(long, value_enum, default_value_t)]
+    units: Units,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -71,19 +79,28 @@ enum OutputFormat {
     Cooklang,
     #[value(alias("md"))]
     Markdown,
+    #[value(alias("htm"))]
+    Html,
+    /// schema.org Recipe as JSON-LD
+    #[value(alias("jsonld"))]
+    SchemaOrg,
 }
 
 pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
-    let mut scale = args.input.scale;
-
-    let input = if let Some(query) = args.input.recipe {
-        let (name, scaling_factor) = split_recipe_name_and_scaling_factor(query.as_str())
-            .map(|(name, scaling_factor)| {
-                let target = scaling_factor.parse::<f64>().unwrap_or_else(|err| {
+    let mut scaling = match (args.input.servings, args.input.scale) {
+        (Some(servings), _) => Scaling::Servings(servings),
+        (None, Some(scale)) => Scaling::Factor(scale),
+        (None, None) => Scaling::Factor(ctx.config().recipe.scale.unwrap_or(1.0)),
+    };
+
+    let (input, content) = if let Some(query) = args.input.recipe {
+        let (name, inline_scaling) = split_recipe_name_and_scaling_factor(query.as_str())
+            .map(|(name, scaling)| {
+                let target = scaling.parse::<Scaling>().unwrap_or_else(|err| {
                     let mut cmd = crate::CliArgs::command();
                     cmd.error(
                         clap::error::ErrorKind::InvalidValue,
-                        format!("Invalid scaling target for '{name}': {err}. Use a number value after @ to specify a scaling factor."),
+                        format!("Invalid scaling target for '{name}': {err:#}"),
                     )
                     .exit()
                 });
@@ -91,24 +108,36 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
             })
             .unwrap_or((query.as_str(), None));
 
-        if let Some(scaling_factor) = scaling_factor {
-            scale = scaling_factor;
+        if let Some(inline_scaling) = inline_scaling {
+            scaling = inline_scaling;
         }
 
-        cooklang_find::get_recipe(vec![ctx.base_path.clone()], name.into())
-            .map_err(|e| anyhow::anyhow!("Recipe not found: {}", e))
+        let entry = cooklang_find::get_recipe(vec![ctx.base_path.clone()], name.into())
+            .map_err(|e| anyhow::anyhow!("Recipe not found: {}", e))?;
+        let path = entry.path().as_ref().context("Recipe has no path")?;
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recipe file: {path}"))?;
+        (entry, content)
     } else {
         let mut buf = String::new();
         std::io::stdin()
             .read_to_string(&mut buf)
             .context("Failed to read stdin")?;
 
-        RecipeEntry::from_content(buf).map_err(|e| anyhow::anyhow!("Failed to parse recipe: {}", e))
-    }?;
+        let entry = RecipeEntry::from_content(buf.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to parse recipe: {}", e))?;
+        (entry, buf)
+    };
 
-    let recipe = input.recipe(scale);
+    let parser = args.units.parser(ctx.parser()?);
+    let mut recipe = parse_recipe(&content, &parser, scaling)?;
+    args.units.convert_recipe(&mut recipe, parser.converter());
+    let scale = scaling.factor(&recipe.metadata)?;
     let title = input.name().as_ref().map_or("", |v| v);
 
+    let default_format =
+        config_format(ctx.config().recipe.format.as_deref())?.unwrap_or(OutputFormat::Human);
+
     let format = args.format.unwrap_or_else(|| match &args.output {
         Some(p) => match p.extension() {
             Some("json") => OutputFormat::Json,
@@ -116,9 +145,11 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
             Some("md") => OutputFormat::Markdown,
             Some("yaml") => OutputFormat::Yaml,
             Some("yml") => OutputFormat::Yaml,
-            _ => OutputFormat::Human,
+            Some("html") | Some("htm") => OutputFormat::Html,
+            Some("jsonld") => OutputFormat::SchemaOrg,
+            _ => default_format,
         },
-        None => OutputFormat::Human,
+        None => default_format,
     });
 
     write_to_output(args.output.as_deref(), |writer| {
@@ -141,13 +172,35 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
                 crate::util::cooklang_to_cooklang::print_cooklang(&recipe, writer)?
             }
             OutputFormat::Yaml => serde_yaml::to_writer(writer, &recipe)?,
-            OutputFormat::Markdown => crate::util::cooklang_to_md::print_md(
+            OutputFormat::Markdown => crate::util::cooklang_to_md::print_md_with_options(
                 &recipe,
                 title,
                 scale,
+                &ctx.config().markdown,
                 ctx.parser()?.converter(),
                 writer,
             )?,
+            OutputFormat::Html => crate::util::cooklang_to_html::print_html(
+                &recipe,
+                title,
+                scale,
+                input.title_image().as_deref(),
+                ctx.parser()?.converter(),
+                writer,
+            )?,
+            OutputFormat::SchemaOrg => {
+                let schema = crate::util::cooklang_to_schema_org::to_schema_org(
+                    &recipe,
+                    title,
+                    scale,
+                    ctx.parser()?.converter(),
+                );
+                if args.pretty {
+                    serde_json::to_writer_pretty(writer, &schema)?;
+                } else {
+                    serde_json::to_writer(writer, &schema)?;
+                }
+            }
         }
 
         Ok(())
@@ -1,4 +1,4 @@
-use crate::util::split_recipe_name_and_scaling_factor;
+use crate::util::{split_recipe_name_and_scaling_factor, Scaling};
 use anyhow::{Context, Result};
 use camino::Utf8PathBuf;
 use clap::{CommandFactory, Parser};
@@ -11,38 +11,59 @@ pub struct ReportArgs {
     #[arg(short, long)]
     template: Utf8PathBuf,
 
-    /// Path to the recipe file (can include scaling factor with :N suffix)
+    /// Path to the recipe file (can include scaling factor with :N suffix,
+    /// or servings with :Np)
     #[arg()]
     recipe: String,
 
+    /// Scale to this number of servings
+    #[arg(long)]
+    servings: Option<u32>,
+
     /// Path to the datastore directory (optional)
     #[arg(short, long)]
     datastore: Option<Utf8PathBuf>,
 }
 
-pub fn run(_ctx: &crate::Context, args: ReportArgs) -> Result<()> {
+pub fn run(ctx: &crate::Context, args: ReportArgs) -> Result<()> {
     // Print warning about prototype feature
     eprintln!("⚠️  Warning: The report command is a prototype feature and will change in future versions.");
 
     // Split recipe name and scaling factor
-    let (recipe_name, scaling_factor) = split_recipe_name_and_scaling_factor(&args.recipe)
+    let (recipe_name, scaling) = split_recipe_name_and_scaling_factor(&args.recipe)
         .map(|(name, factor)| {
-            let scale = factor.parse::<f64>().unwrap_or_else(|err| {
+            let scaling = factor.parse::<Scaling>().unwrap_or_else(|err| {
                 let mut cmd = crate::CliArgs::command();
                 cmd.error(
                     clap::error::ErrorKind::InvalidValue,
-                    format!("Invalid scaling factor for '{name}': {err}"),
+                    format!("Invalid scaling factor for '{name}': {err:#}"),
                 )
                 .exit()
             });
-            (name, scale)
+            (name, scaling)
         })
-        .unwrap_or((&args.recipe, 1.0));
+        .unwrap_or((
+            &args.recipe,
+            args.servings
+                .map_or(Scaling::Factor(1.0), Scaling::Servings),
+        ));
 
     // Read the recipe file
     let recipe = fs::read_to_string(recipe_name)
         .with_context(|| format!("Failed to read recipe file: {}", recipe_name))?;
 
+    let scaling_factor = match scaling {
+        Scaling::Factor(factor) => factor,
+        Scaling::Servings(_) => {
+            let metadata = ctx
+                .parser()?
+                .parse_metadata(&recipe)
+                .into_output()
+                .with_context(|| format!("Invalid metadata in recipe: {recipe_name}"))?;
+            scaling.factor(&metadata)?
+        }
+    };
+
     // Read the template file
     let template = fs::read_to_string(&args.template)
         .with_context(|| format!("Failed to read template file: {}", args.template))?;
@@ -1,31 +1,95 @@
+use std::io::Write;
+
 use anyhow::Result;
 use camino::Utf8PathBuf;
-use clap::Args;
-use cooklang_find::search;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
 
-use crate::Context;
+use crate::{
+    util::{index, search::Query},
+    Context,
+};
 
 #[derive(Debug, Args)]
 pub struct SearchArgs {
     /// Search query
+    ///
+    /// Words are matched against recipe names and text. Filters narrow down
+    /// the results: `ingredient:garlic`, `cookware:wok`, `tag:vegan`,
+    /// `path:Breakfast/`, `name:soup`, `time<30m`, `servings>=4`. Prefix any
+    /// term with `-` to exclude it, e.g. `-ingredient:pork`.
     #[arg(required = true)]
-    query: String,
+    query: Vec<String>,
 
     /// Base directory to search in
     #[arg(short, long)]
     base_dir: Option<Utf8PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+    #[value(alias("yml"))]
+    Yaml,
+}
+
+#[derive(Serialize)]
+struct SearchHit<'a> {
+    name: &'a str,
+    path: &'a str,
+    score: f64,
+    tags: &'a [String],
+    servings: Option<u32>,
+    /// Minutes
+    total_time: Option<u32>,
 }
 
 pub fn run(ctx: &Context, args: SearchArgs) -> Result<()> {
     let base_dir = args.base_dir.unwrap_or_else(|| ctx.base_path.clone());
 
-    let recipes = search(&base_dir, &args.query)?;
+    let query: Query = args.query.join(" ").parse()?;
+    let recipes = index::recipes(&base_dir, ctx.parser()?)?;
+    let results = query.search(&recipes);
 
-    for recipe in recipes {
-        if let Some(path) = recipe.path() {
-            let relative_path = path.strip_prefix(&base_dir).unwrap_or(path);
-            println!("{}", relative_path);
+    let hits = || {
+        results
+            .iter()
+            .map(|&(recipe, score)| SearchHit {
+                name: &recipe.name,
+                path: &recipe.path,
+                score,
+                tags: &recipe.tags,
+                servings: recipe.servings,
+                total_time: recipe.total_time,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        OutputFormat::Human => {
+            for (recipe, _) in &results {
+                writeln!(out, "{}", recipe.path)?;
+            }
+        }
+        OutputFormat::Json => {
+            if args.pretty {
+                serde_json::to_writer_pretty(&mut out, &hits())?;
+            } else {
+                serde_json::to_writer(&mut out, &hits())?;
+            }
+            writeln!(out)?;
         }
+        OutputFormat::Yaml => serde_yaml::to_writer(&mut out, &hits())?,
     }
 
     Ok(())
@@ -0,0 +1,402 @@
+use super::recipes::check_path;
+use crate::server::AppState;
+use crate::util::diagnostic::Diagnostic;
+use axum::{
+    extract::{Path, Request, State},
+    http::{
+        header::{HOST, ORIGIN},
+        StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Same as `cooklang_find`, so the title image moves with the recipe
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Status and a JSON body with an `error` message
+type ApiError = (StatusCode, Json<Value>);
+
+type EditResult = Result<(StatusCode, Json<Value>), ApiError>;
+
+#[derive(Deserialize)]
+pub struct NewRecipe {
+    path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+pub struct MoveRecipe {
+    from: String,
+    to: String,
+}
+
+/// Create a new recipe from `{ "path": ..., "content": ... }`
+pub async fn create_recipe(
+    State(state): State<Arc<AppState>>,
+    Json(new): Json<NewRecipe>,
+) -> EditResult {
+    let (relative, file) = recipe_file(&state, &new.path)?;
+    if file.exists() {
+        return Err(error(
+            StatusCode::CONFLICT,
+            format!("{relative} already exists"),
+        ));
+    }
+    let diagnostics = validate(&state, &new.content)?;
+
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).map_err(internal_error)?;
+    }
+    std::fs::write(&file, &new.content).map_err(internal_error)?;
+    state.invalidate();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "path": relative, "diagnostics": diagnostics })),
+    ))
+}
+
+/// Replace the content of an existing recipe with the request body
+pub async fn update_recipe(
+    Path(path): Path<String>,
+    State(state): State<Arc<AppState>>,
+    content: String,
+) -> EditResult {
+    let (relative, file) = recipe_file(&state, &path)?;
+    if !file.is_file() {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("{relative} not found"),
+        ));
+    }
+    let diagnostics = validate(&state, &content)?;
+
+    std::fs::write(&file, &content).map_err(internal_error)?;
+    state.invalidate();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "path": relative, "diagnostics": diagnostics })),
+    ))
+}
+
+pub async fn delete_recipe(
+    Path(path): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode, ApiError> {
+    let (relative, file) = recipe_file(&state, &path)?;
+    if !file.is_file() {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("{relative} not found"),
+        ));
+    }
+
+    std::fs::remove_file(&file).map_err(internal_error)?;
+    state.invalidate();
+    tracing::info!("Deleted {relative}");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rename or move a recipe from `{ "from": ..., "to": ... }`
+///
+/// Its title image, if any, is moved too.
+pub async fn move_recipe(
+    State(state): State<Arc<AppState>>,
+    Json(paths): Json<MoveRecipe>,
+) -> EditResult {
+    let (from_relative, from) = recipe_file(&state, &paths.from)?;
+    let (to_relative, to) = recipe_file(&state, &paths.to)?;
+    if !from.is_file() {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("{from_relative} not found"),
+        ));
+    }
+    if to.exists() {
+        return Err(error(
+            StatusCode::CONFLICT,
+            format!("{to_relative} already exists"),
+        ));
+    }
+
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(internal_error)?;
+    }
+    std::fs::rename(&from, &to).map_err(internal_error)?;
+    for ext in IMAGE_EXTENSIONS {
+        let image = from.with_extension(ext);
+        if image.is_file() {
+            let target = to.with_extension(ext);
+            if let Err(e) = std::fs::rename(&image, &target) {
+                tracing::warn!("Failed to move image {image}: {e}");
+            }
+        }
+    }
+    state.invalidate();
+    tracing::info!("Moved {from_relative} to {to_relative}");
+
+    Ok((StatusCode::OK, Json(json!({ "path": to_relative }))))
+}
+
+/// Rejects requests sent by pages of other sites
+///
+/// Browsers add the `Origin` of the page, other clients usually don't.
+pub async fn same_origin(request: Request, next: Next) -> Result<Response, ApiError> {
+    let headers = request.headers();
+    if let Some(origin) = headers.get(ORIGIN) {
+        let origin_host = origin
+            .to_str()
+            .ok()
+            .and_then(|o| o.split_once("://"))
+            .map(|(_, host)| host);
+        let host = headers.get(HOST).and_then(|h| h.to_str().ok());
+        if origin_host.is_none() || origin_host != host {
+            return Err(error(
+                StatusCode::FORBIDDEN,
+                format!("Recipes can't be edited from {origin:?}"),
+            ));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// Rejects every edit when the server was not started with `--edit`
+pub async fn read_only(_request: Request, _next: Next) -> ApiError {
+    error(
+        StatusCode::FORBIDDEN,
+        "Recipes can't be edited, start the server with --edit".to_string(),
+    )
+}
+
+/// Relative and absolute path of a recipe file, adding the `.cook` extension
+/// if missing
+fn recipe_file(state: &AppState, path: &str) -> Result<(Utf8PathBuf, Utf8PathBuf), ApiError> {
+    check_path(path).map_err(|status| error(status, format!("Invalid path: {path}")))?;
+
+    let mut relative = Utf8PathBuf::from(path);
+    match relative.extension() {
+        None => {
+            relative.set_extension("cook");
+        }
+        Some("cook") => {}
+        Some(_) => {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                format!("Recipe files must have the .cook extension: {path}"),
+            ))
+        }
+    }
+
+    let file = state.base_path.join(&relative);
+    Ok((relative, file))
+}
+
+/// Parse the content, failing with the diagnostics if it has errors
+///
+/// On success, the warnings are returned.
+fn validate(state: &AppState, content: &str) -> Result<Vec<Diagnostic>, ApiError> {
+    let report = state.parser.parse(content).into_report();
+    let diagnostics = report
+        .iter()
+        .map(|d| Diagnostic::new(d, content))
+        .collect::<Vec<_>>();
+
+    if report.has_errors() {
+        let body = json!({
+            "error": "The recipe has errors",
+            "diagnostics": diagnostics,
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(body)));
+    }
+    Ok(diagnostics)
+}
+
+fn error(status: StatusCode, message: String) -> ApiError {
+    tracing::error!("{message}");
+    (status, Json(json!({ "error": message })))
+}
+
+fn internal_error(e: std::io::Error) -> ApiError {
+    error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct Server {
+        dir: tempfile::TempDir,
+        app: Router,
+    }
+
+    impl Server {
+        fn new(edit: bool) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let base_path = camino::Utf8Path::from_path(dir.path()).unwrap();
+            std::fs::write(base_path.join("Bread.cook"), "Bake.").unwrap();
+            let app = crate::server::app(AppState::for_tests(base_path), edit).unwrap();
+            Self { dir, app }
+        }
+
+        fn read(&self, path: &str) -> Option<String> {
+            std::fs::read_to_string(self.dir.path().join(path)).ok()
+        }
+
+        async fn send(
+            &self,
+            method: &str,
+            uri: &str,
+            body: impl Into<String>,
+            origin: Option<&str>,
+        ) -> (StatusCode, Value) {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(HOST, "localhost:9080")
+                .header("content-type", "application/json");
+            if let Some(origin) = origin {
+                request = request.header(ORIGIN, origin);
+            }
+            let request = request.body(Body::from(body.into())).unwrap();
+
+            let response = self.app.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+        }
+    }
+
+    #[tokio::test]
+    async fn edit_recipes() {
+        let server = Server::new(true);
+
+        let new = json!({ "path": "Lunch/Toast", "content": "Toast @bread{1}." });
+        let (status, body) = server
+            .send("POST", "/api/recipes", new.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["path"], "Lunch/Toast.cook");
+        assert_eq!(server.read("Lunch/Toast.cook").unwrap(), "Toast @bread{1}.");
+        let (status, _) = server
+            .send("POST", "/api/recipes", new.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, _) = server
+            .send(
+                "PUT",
+                "/api/recipes/Lunch/Toast.cook",
+                "Toast @bread{2}.",
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(server.read("Lunch/Toast.cook").unwrap(), "Toast @bread{2}.");
+        let (status, _) = server
+            .send("PUT", "/api/recipes/Missing", "Mix.", None)
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        std::fs::write(server.dir.path().join("Lunch/Toast.jpg"), "jpg").unwrap();
+        let paths = json!({ "from": "Lunch/Toast", "to": "Breakfast/Toast" });
+        let (status, body) = server
+            .send("POST", "/api/move_recipe", paths.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["path"], "Breakfast/Toast.cook");
+        assert!(server.read("Lunch/Toast.cook").is_none());
+        assert!(server.read("Breakfast/Toast.cook").is_some());
+        assert_eq!(server.read("Breakfast/Toast.jpg").unwrap(), "jpg");
+
+        let (status, _) = server
+            .send("DELETE", "/api/recipes/Breakfast/Toast", "", None)
+            .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(server.read("Breakfast/Toast.cook").is_none());
+        let (status, _) = server
+            .send("DELETE", "/api/recipes/Breakfast/Toast", "", None)
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn invalid_requests() {
+        let server = Server::new(true);
+
+        for path in ["../Evil", "/tmp/Evil", "Lunch/../../Evil"] {
+            let new = json!({ "path": path, "content": "Mix." });
+            let (status, _) = server
+                .send("POST", "/api/recipes", new.to_string(), None)
+                .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{path}");
+        }
+        let paths = json!({ "from": "Bread", "to": "../Bread" });
+        let (status, _) = server
+            .send("POST", "/api/move_recipe", paths.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let new = json!({ "path": "Notes.txt", "content": "Mix." });
+        let (status, _) = server
+            .send("POST", "/api/recipes", new.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = server
+            .send("PUT", "/api/recipes/Bread", "Wait ~{}.", None)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "The recipe has errors");
+        assert_eq!(body["diagnostics"][0]["severity"], "error");
+        assert_eq!(server.read("Bread.cook").unwrap(), "Bake.");
+    }
+
+    #[tokio::test]
+    async fn forbidden() {
+        let server = Server::new(true);
+        for origin in ["http://evil.example", "null"] {
+            let (status, _) = server
+                .send("DELETE", "/api/recipes/Bread", "", Some(origin))
+                .await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{origin}");
+        }
+        assert!(server.read("Bread.cook").is_some());
+        let (status, _) = server
+            .send(
+                "PUT",
+                "/api/recipes/Bread",
+                "Mix.",
+                Some("http://localhost:9080"),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // without --edit
+        let server = Server::new(false);
+        let (status, _) = server.send("DELETE", "/api/recipes/Bread", "", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let new = json!({ "path": "Toast", "content": "Toast." });
+        let (status, _) = server
+            .send("POST", "/api/recipes", new.to_string(), None)
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(server.read("Bread.cook").is_some());
+        assert!(server.read("Toast.cook").is_none());
+
+        // reading is still allowed
+        let (status, _) = server.send("GET", "/api/recipes/Bread", "", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
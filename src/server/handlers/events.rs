@@ -0,0 +1,44 @@
+use crate::server::AppState;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::Stream;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Server-Sent Events with a `change` event for every file change
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let changes = state.events.subscribe();
+    let shutdown = state.shutdown.subscribe();
+
+    let stream = futures_util::stream::unfold(
+        (changes, shutdown),
+        |(mut changes, mut shutdown)| async move {
+            loop {
+                let change = tokio::select! {
+                    change = changes.recv() => change,
+                    // end the stream so the server can shut down gracefully
+                    _ = shutdown.changed() => return None,
+                };
+                match change {
+                    Ok(change) => {
+                        let event = Event::default()
+                            .event("change")
+                            .json_data(&change)
+                            .unwrap_or_else(|_| Event::default().event("change"));
+                        return Some((Ok(event), (changes, shutdown)));
+                    }
+                    // some events were dropped, the next one is enough to
+                    // make the client refresh
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -1,5 +1,9 @@
+pub mod edit;
+pub mod events;
 pub mod recipes;
 pub mod shopping_list;
 
+pub use edit::{create_recipe, delete_recipe, move_recipe, read_only, same_origin, update_recipe};
+pub use events::events;
 pub use recipes::{all_recipes, recipe, search};
 pub use shopping_list::shopping_list;
@@ -1,4 +1,5 @@
 use crate::server::AppState;
+use crate::util::{parse_recipe_entry, recipe_metadata, units::Units, Scaling};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -8,17 +9,16 @@ use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use cooklang_find;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::sync::Arc;
-
-#[derive(Debug, Deserialize, Clone, Copy, Default)]
-#[serde(default)]
-struct ColorConfig {
-    color: bool,
-}
+use std::{collections::BTreeMap, sync::Arc};
 
 #[derive(Deserialize)]
 pub struct RecipeQuery {
-    scale: Option<f64>,
+    /// A factor like `2` or servings like `4p`
+    scale: Option<String>,
+    servings: Option<u32>,
+    /// `metric`, `imperial` or `original`
+    #[serde(default)]
+    units: Units,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,7 +26,7 @@ pub struct SearchQuery {
     q: String,
 }
 
-fn check_path(p: &str) -> Result<(), StatusCode> {
+pub(super) fn check_path(p: &str) -> Result<(), StatusCode> {
     let path = Utf8Path::new(p);
     if !path
         .components()
@@ -38,15 +38,74 @@ fn check_path(p: &str) -> Result<(), StatusCode> {
     Ok(())
 }
 
+/// Same shape as [`cooklang_find::RecipeTree`], built from the index
+#[derive(Serialize)]
+struct RecipeTree<'a> {
+    name: String,
+    path: Utf8PathBuf,
+    recipe: Option<TreeRecipe<'a>>,
+    children: BTreeMap<String, RecipeTree<'a>>,
+}
+
+#[derive(Serialize)]
+struct TreeRecipe<'a> {
+    path: Utf8PathBuf,
+    metadata: &'a cooklang::Metadata,
+}
+
+impl RecipeTree<'_> {
+    fn new(name: String, path: Utf8PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            recipe: None,
+            children: BTreeMap::new(),
+        }
+    }
+}
+
 pub async fn all_recipes(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let recipes = cooklang_find::build_tree(&state.base_path).map_err(|e| {
+    let recipes = state.recipes().map_err(|e| {
         tracing::error!("Failed to build recipe tree: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let recipes = serde_json::to_value(recipes).map_err(|e| {
+    let base_name = state
+        .base_path
+        .file_name()
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| String::from("./"));
+    let mut root = RecipeTree::new(base_name, state.base_path.clone());
+
+    for info in recipes.iter() {
+        let rel_path = Utf8Path::new(&info.path);
+        let mut current = &mut root;
+        for component in rel_path.parent().into_iter().flat_map(|p| p.components()) {
+            let name = component.to_string();
+            let path = current.path.join(&name);
+            current = current
+                .children
+                .entry(name.clone())
+                .or_insert_with(|| RecipeTree::new(name, path));
+        }
+
+        let name = info.title().to_string();
+        let path = state.base_path.join(rel_path);
+        current.children.insert(
+            name.clone(),
+            RecipeTree {
+                recipe: Some(TreeRecipe {
+                    path: path.clone(),
+                    metadata: &info.metadata,
+                }),
+                ..RecipeTree::new(name, path)
+            },
+        );
+    }
+
+    let recipes = serde_json::to_value(root).map_err(|e| {
         tracing::error!("Failed to serialize recipes: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
@@ -67,12 +126,37 @@ pub async fn recipe(
             StatusCode::NOT_FOUND
         })?;
 
-    let recipe = entry.recipe(query.scale.unwrap_or(1.0));
+    let scaling = match (query.servings, query.scale) {
+        (Some(servings), _) => Scaling::Servings(servings),
+        (None, Some(scale)) => scale.parse().map_err(|e| {
+            tracing::error!("Invalid scale for {path}: {e:#}");
+            StatusCode::BAD_REQUEST
+        })?,
+        (None, None) => Scaling::Factor(state.default_scale),
+    };
+    // check servings first, so a recipe without them is a bad request
+    if let Scaling::Servings(_) = scaling {
+        let metadata = recipe_metadata(&state.base_path, &path, &state.parser).map_err(|e| {
+            tracing::error!("Failed to read metadata of {path}: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        scaling.factor(&metadata).map_err(|e| {
+            tracing::error!("{path}: {e:#}");
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+
+    let parser = query.units.parser(&state.parser);
+    let mut recipe = parse_recipe_entry(&entry, &parser, scaling).map_err(|e| {
+        tracing::error!("Failed to parse recipe {path}: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    query.units.convert_recipe(&mut recipe, parser.converter());
 
     #[derive(Serialize)]
     struct ApiRecipe {
         #[serde(flatten)]
-        recipe: Arc<cooklang::ScaledRecipe>,
+        recipe: cooklang::ScaledRecipe,
         grouped_ingredients: Vec<serde_json::Value>,
     }
 
@@ -107,19 +191,23 @@ pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
 ) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    let recipes = cooklang_find::search(&state.base_path, &query.q).map_err(|e| {
+    let search_query: crate::util::search::Query = query.q.parse().map_err(|e| {
+        tracing::error!("Invalid search query: {:?}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let recipes = state.recipes().map_err(|e| {
         tracing::error!("Failed to search recipes: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let results = recipes
+    let results = search_query
+        .search(&recipes)
         .into_iter()
-        .map(|recipe| {
-            let path = recipe.path().as_ref().unwrap();
-            let relative_path = path.strip_prefix(&state.base_path).unwrap_or(path);
+        .map(|(recipe, _)| {
             serde_json::json!({
-                "name": recipe.name(),
-                "path": relative_path.to_string()
+                "name": recipe.title(),
+                "path": recipe.path
             })
         })
         .collect();
@@ -1,5 +1,5 @@
 use crate::server::AppState;
-use crate::util::extract_ingredients;
+use crate::util::{extract_ingredients, sources::Sources};
 use axum::{extract::State, http::StatusCode, Json};
 use cooklang::ingredient_list::IngredientList;
 use serde_json;
@@ -12,6 +12,7 @@ pub async fn shopping_list(
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let mut list = IngredientList::new();
     let mut seen = BTreeMap::new();
+    let mut sources = Sources::new();
 
     for entry in payload {
         extract_ingredients(
@@ -19,8 +20,9 @@ pub async fn shopping_list(
             &mut list,
             &mut seen,
             &state.base_path,
-            state.parser.converter(),
+            &state.parser,
             false,
+            Some(&mut sources),
         )
         .map_err(|e| {
             tracing::error!("Error processing recipe: {}", e);
@@ -44,8 +46,9 @@ pub async fn shopping_list(
                 "category": category,
                 "items": items.into_iter().map(|(name, qty)| {
                     serde_json::json!({
+                        "sources": sources.get(&name),
                         "name": name,
-                        "quantities": qty.into_vec()
+                        "quantities": qty.into_vec(),
                     })
                 }).collect::<Vec<_>>()
             })
@@ -53,3 +56,35 @@ pub async fn shopping_list(
     });
     Ok(Json(json_value))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn status(entries: &[&str]) -> StatusCode {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = camino::Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::write(base_path.join("Bread.cook"), "Mix @flour{500%g}.").unwrap();
+        let app = crate::server::app(AppState::for_tests(base_path), false).unwrap();
+
+        let request = Request::post("/api/shopping_list")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(entries).unwrap()))
+            .unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn invalid_entries() {
+        assert_eq!(
+            status(&["Bread", "Bread:2p"]).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(status(&["Bread:lots"]).await, StatusCode::BAD_REQUEST);
+        assert_eq!(status(&["Missing"]).await, StatusCode::BAD_REQUEST);
+        assert_eq!(status(&["Bread:2"]).await, StatusCode::OK);
+    }
+}
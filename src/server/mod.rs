@@ -28,23 +28,34 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::util::resolve_to_absolute_path;
+use crate::util::{index, resolve_to_absolute_path, search::RecipeInfo};
 use crate::Context;
 use anyhow::{bail, Result};
 use axum::{
     http::{HeaderValue, Method},
-    routing::{get, post},
+    middleware,
+    routing::{get, post, put},
     Router,
 };
 use camino::Utf8PathBuf;
 use clap::Args;
 use cooklang::CooklangParser;
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+use tokio::sync::{broadcast, watch};
 use tower_http::cors::CorsLayer;
 use tracing::info;
 
 mod handlers;
 mod ui;
+mod watcher;
+
+const DEFAULT_PORT: u16 = 9080;
 
 #[derive(Debug, Args)]
 pub struct ServerArgs {
@@ -52,17 +63,30 @@ pub struct ServerArgs {
     base_path: Option<Utf8PathBuf>,
 
     /// Allow external connections
-    #[arg(long)]
-    host: bool,
+    ///
+    /// Overrides `server.host` from the config file, use `--host=false` to
+    /// turn it off.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    host: Option<bool>,
 
-    /// Set http server port
-    #[arg(long, default_value_t = 9080)]
-    port: u16,
+    /// Set http server port [default: 9080]
+    #[arg(long)]
+    port: Option<u16>,
 
     /// Open browser on start
+    ///
+    /// Overrides `server.open` from the config file, use `--open=false` to
+    /// turn it off.
     // #[cfg(feature = "ui")]
-    #[arg(long, default_value_t = false)]
-    open: bool,
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    open: Option<bool>,
+
+    /// Allow creating, changing and removing recipes through the API
+    ///
+    /// Overrides `server.edit` from the config file, use `--edit=false` to
+    /// turn it off.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    edit: Option<bool>,
 }
 
 impl ServerArgs {
@@ -73,17 +97,21 @@ impl ServerArgs {
 
 #[tokio::main]
 pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
-    let addr = if args.host {
-        SocketAddr::from(([0, 0, 0, 0], args.port))
+    let config = &ctx.config().server;
+    let port = args.port.or(config.port).unwrap_or(DEFAULT_PORT);
+
+    let addr = if args.host.unwrap_or(config.host) {
+        SocketAddr::from(([0, 0, 0, 0], port))
     } else {
-        SocketAddr::from(([127, 0, 0, 1], args.port))
+        SocketAddr::from(([127, 0, 0, 1], port))
     };
 
     info!("Listening on http://{addr}");
 
+    let edit = args.edit.unwrap_or(config.edit);
+
     // #[cfg(feature = "ui")]
-    if args.open {
-        let port = args.port;
+    if args.open.unwrap_or(config.open) {
         let url = format!("http://localhost:{port}");
         info!("Serving web UI on {url}");
         tokio::task::spawn(async move {
@@ -96,19 +124,17 @@ pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
 
     let state = build_state(ctx, args)?;
 
-    let app = Router::new().nest("/api", api(&state)?);
-
-    let app = app.merge(ui::ui());
+    watcher::spawn(state.clone());
 
-    let app = app.with_state(state).layer(
-        CorsLayer::new()
-            .allow_origin("*".parse::<HeaderValue>().unwrap())
-            .allow_methods([Method::GET, Method::POST]),
-    );
+    if edit {
+        info!("Recipes can be edited through the API");
+    }
+    let app = app(state.clone(), edit)?;
+    let state_for_shutdown = state;
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(shutdown_signal(state_for_shutdown))
         .await
         .unwrap();
 
@@ -120,6 +146,7 @@ pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
 fn build_state(ctx: Context, args: ServerArgs) -> Result<Arc<AppState>> {
     ctx.parser()?;
     let aisle_path = ctx.aisle().clone();
+    let default_scale = ctx.config().recipe.scale.unwrap_or(1.0);
     let Context {
         parser, base_path, ..
     } = ctx;
@@ -138,10 +165,15 @@ fn build_state(ctx: Context, args: ServerArgs) -> Result<Arc<AppState>> {
         parser,
         base_path: absolute_path,
         aisle_path,
+        default_scale,
+        recipes: RwLock::new(None),
+        generation: AtomicU64::new(0),
+        events: broadcast::channel(64).0,
+        shutdown: watch::channel(false).0,
     }))
 }
 
-async fn shutdown_signal() {
+async fn shutdown_signal(state: Arc<AppState>) {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -165,12 +197,64 @@ async fn shutdown_signal() {
     };
 
     info!("Stopping server");
+    // close open event streams, or the shutdown would wait for them forever
+    let _ = state.shutdown.send(true);
 }
 
 pub struct AppState {
     parser: CooklangParser,
     base_path: Utf8PathBuf,
     aisle_path: Option<Utf8PathBuf>,
+    default_scale: f64,
+    /// Parsed recipes, until the watcher sees a change
+    recipes: RwLock<Option<Arc<Vec<RecipeInfo>>>>,
+    /// Incremented on every invalidation
+    generation: AtomicU64,
+    events: broadcast::Sender<watcher::ChangeEvent>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl AppState {
+    /// Information of every recipe, cached in memory
+    fn recipes(&self) -> Result<Arc<Vec<RecipeInfo>>> {
+        if let Some(recipes) = self.recipes.read().unwrap().as_ref() {
+            return Ok(recipes.clone());
+        }
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let recipes = Arc::new(index::recipes(&self.base_path, &self.parser)?);
+
+        // don't store it if something changed while loading
+        let mut cache = self.recipes.write().unwrap();
+        if self.generation.load(Ordering::Acquire) == generation {
+            *cache = Some(recipes.clone());
+        }
+        Ok(recipes)
+    }
+
+    /// Drop cached recipes, so the next request reads the files again
+    fn invalidate(&self) {
+        let mut cache = self.recipes.write().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        *cache = None;
+    }
+}
+
+fn app(state: Arc<AppState>, edit: bool) -> Result<Router> {
+    // any page can read the recipes, but only the server's own can change them
+    let api = api(&state)?
+        .layer(
+            CorsLayer::new()
+                .allow_origin("*".parse::<HeaderValue>().unwrap())
+                .allow_methods([Method::GET, Method::POST]),
+        )
+        .merge(edit_api(edit));
+
+    let app = Router::new().nest("/api", api);
+
+    let app = app.merge(ui::ui());
+
+    Ok(app.with_state(state))
 }
 
 fn api(_state: &AppState) -> Result<Router<Arc<AppState>>> {
@@ -178,7 +262,65 @@ fn api(_state: &AppState) -> Result<Router<Arc<AppState>>> {
         .route("/shopping_list", post(handlers::shopping_list))
         .route("/recipes", get(handlers::all_recipes))
         .route("/recipes/{*path}", get(handlers::recipe))
-        .route("/search", get(handlers::search));
+        .route("/search", get(handlers::search))
+        .route("/events", get(handlers::events));
 
     Ok(router)
 }
+
+/// Routes that write to the base path, forbidden without `--edit`
+fn edit_api(edit: bool) -> Router<Arc<AppState>> {
+    let router = Router::new()
+        .route("/recipes", post(handlers::create_recipe))
+        .route(
+            "/recipes/{*path}",
+            put(handlers::update_recipe).delete(handlers::delete_recipe),
+        )
+        .route("/move_recipe", post(handlers::move_recipe));
+    if edit {
+        router.route_layer(middleware::from_fn(handlers::same_origin))
+    } else {
+        router.route_layer(middleware::from_fn(handlers::read_only))
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State of a server for the recipes in `base_path`
+    fn for_tests(base_path: &camino::Utf8Path) -> Arc<Self> {
+        let args = ServerArgs {
+            base_path: None,
+            host: None,
+            port: None,
+            open: None,
+            edit: None,
+        };
+        build_state(Context::for_tests(base_path), args).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipes_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        std::fs::write(base_path.join("Bread.cook"), "Bake.").unwrap();
+        let state = AppState::for_tests(&base_path);
+
+        let recipes = state.recipes().unwrap();
+        assert_eq!(recipes.len(), 1);
+
+        // served from memory until invalidated
+        std::fs::write(base_path.join("Toast.cook"), "Toast.").unwrap();
+        assert!(Arc::ptr_eq(&state.recipes().unwrap(), &recipes));
+
+        state.invalidate();
+        assert_eq!(state.generation.load(Ordering::Acquire), 1);
+        let recipes = state.recipes().unwrap();
+        assert_eq!(recipes.len(), 2);
+        assert!(Arc::ptr_eq(&state.recipes().unwrap(), &recipes));
+    }
+}
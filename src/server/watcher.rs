@@ -0,0 +1,255 @@
+//! Watch the base path for recipe changes
+//!
+//! The OS file events are used when available. Where they are not, like some
+//! network drives, the directory is polled every few seconds instead.
+
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use notify::{
+    event::{EventKind, ModifyKind},
+    Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use serde::Serialize;
+use tokio::{sync::mpsc, time::Duration};
+use tracing::{debug, warn};
+
+use super::AppState;
+
+/// Only used when the OS events are not available
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time to wait for more events, editors usually make a few for a save
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Sent to clients when a file changes
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    /// Relative to the base path when inside it
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// Start watching for changes in the background
+///
+/// On every change the cached recipes are invalidated and a [`ChangeEvent`]
+/// is sent to subscribers.
+pub fn spawn(state: Arc<AppState>) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let handler = move |event: notify::Result<notify::Event>| match event {
+        Ok(event) => {
+            let _ = tx.send(event);
+        }
+        Err(e) => warn!("Failed to watch for changes: {e}"),
+    };
+
+    let watcher = match watch(&state, handler) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Failed to watch for changes: {e:#}");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        // dropping the watcher stops it
+        let _watcher = watcher;
+        while let Some(event) = rx.recv().await {
+            let mut events = vec![event];
+            tokio::time::sleep(DEBOUNCE).await;
+            while let Ok(event) = rx.try_recv() {
+                events.push(event);
+            }
+
+            let (invalidate, changes) = changes(&events, &state);
+            if !invalidate {
+                continue;
+            }
+            state.invalidate();
+            for change in changes {
+                debug!("{:?} {}", change.kind, change.path);
+                // no receivers is fine
+                let _ = state.events.send(change);
+            }
+        }
+    });
+}
+
+fn watch<H>(state: &AppState, handler: H) -> notify::Result<Box<dyn Watcher + Send>>
+where
+    H: notify::EventHandler + Clone,
+{
+    let mut watcher: Box<dyn Watcher + Send> =
+        match RecommendedWatcher::new(handler.clone(), Config::default()) {
+            Ok(watcher) => Box::new(watcher),
+            Err(e) => {
+                warn!("File events not available, polling for changes: {e}");
+                let config = Config::default().with_poll_interval(POLL_INTERVAL);
+                Box::new(PollWatcher::new(handler, config)?)
+            }
+        };
+
+    watcher.watch(state.base_path.as_std_path(), RecursiveMode::Recursive)?;
+    // the directory and not the file, which editors may replace
+    let aisle_dir = state.aisle_path.as_ref().and_then(|p| p.parent());
+    if let Some(dir) = aisle_dir.filter(|d| d.is_dir() && !d.starts_with(&state.base_path)) {
+        watcher.watch(dir.as_std_path(), RecursiveMode::NonRecursive)?;
+    }
+    Ok(watcher)
+}
+
+/// Whether the cache has to be invalidated, and the recipe and aisle files
+/// that changed, once each
+fn changes(events: &[notify::Event], state: &AppState) -> (bool, Vec<ChangeEvent>) {
+    let mut invalidate = false;
+    let mut changed = BTreeMap::new();
+    for event in events {
+        let kind = match event.kind {
+            EventKind::Create(_) => ChangeKind::Created,
+            EventKind::Remove(_) => ChangeKind::Removed,
+            EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Created,
+            EventKind::Modify(_) | EventKind::Any => ChangeKind::Modified,
+            EventKind::Access(_) | EventKind::Other => continue,
+        };
+        for path in event.paths.iter().filter_map(|p| utf8(p)) {
+            let is_aisle = state.aisle_path.as_ref() == Some(&path);
+            if !is_aisle
+                && (!path.starts_with(&state.base_path) || is_hidden(&path, &state.base_path))
+            {
+                continue;
+            }
+            if is_aisle || path.extension() == Some("cook") {
+                invalidate = true;
+                // renames only say where the file is now or was before
+                let kind = match kind {
+                    ChangeKind::Created if !path.exists() => ChangeKind::Removed,
+                    kind => kind,
+                };
+                let kind = match (changed.get(&path), kind) {
+                    // writing a new file also modifies it
+                    (Some(ChangeKind::Created), ChangeKind::Modified) => ChangeKind::Created,
+                    // editors that replace the file instead of writing it
+                    (Some(ChangeKind::Removed), ChangeKind::Created) => ChangeKind::Modified,
+                    (_, kind) => kind,
+                };
+                changed.insert(path, kind);
+            } else if path.extension().is_none() {
+                // a directory, its recipes may have been moved or removed
+                invalidate = true;
+            }
+        }
+    }
+
+    let changes = changed
+        .into_iter()
+        .map(|(path, kind)| ChangeEvent {
+            kind,
+            path: path
+                .strip_prefix(&state.base_path)
+                .unwrap_or(&path)
+                .to_string(),
+        })
+        .collect();
+    (invalidate, changes)
+}
+
+fn utf8(path: &std::path::Path) -> Option<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(PathBuf::from(path)).ok()
+}
+
+/// Like the files [`find_recipe_files`](crate::util::find_recipe_files) skips
+fn is_hidden(path: &Utf8Path, base_path: &Utf8Path) -> bool {
+    path.strip_prefix(base_path)
+        .unwrap_or(path)
+        .components()
+        .any(|c| c.as_str().starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+    use tokio::time::timeout;
+
+    use super::*;
+
+    fn changes_of(state: &AppState, events: &[(EventKind, &Utf8Path)]) -> (bool, Vec<String>) {
+        let events = events
+            .iter()
+            .map(|(kind, path)| notify::Event::new(*kind).add_path(path.into()))
+            .collect::<Vec<_>>();
+        let (invalidate, changes) = changes(&events, state);
+        let changes = changes
+            .into_iter()
+            .map(|c| format!("{:?} {}", c.kind, c.path))
+            .collect();
+        (invalidate, changes)
+    }
+
+    #[test]
+    fn merges_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        let state = AppState::for_tests(&base_path);
+        let bread = &base_path.join("Bread.cook");
+        let notes = &base_path.join("notes.txt");
+        let hidden = &base_path.join(".git/Bread.cook");
+        std::fs::write(bread, "Bake.").unwrap();
+
+        let create = EventKind::Create(CreateKind::File);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let remove = EventKind::Remove(RemoveKind::File);
+
+        assert_eq!(
+            changes_of(
+                &state,
+                &[
+                    (create, bread),
+                    (modify, bread),
+                    (modify, notes),
+                    (modify, hidden)
+                ]
+            ),
+            (true, vec!["Created Bread.cook".to_string()])
+        );
+        // replaced by an editor
+        assert_eq!(
+            changes_of(&state, &[(remove, bread), (create, bread)]),
+            (true, vec!["Modified Bread.cook".to_string()])
+        );
+        assert_eq!(
+            changes_of(&state, &[(modify, notes), (create, hidden)]),
+            (false, vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn one_event_per_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        let state = AppState::for_tests(&base_path);
+        let mut events = state.events.subscribe();
+        spawn(state.clone());
+        assert!(state.recipes().unwrap().is_empty());
+
+        std::fs::write(base_path.join("Bread.cook"), "Bake.").unwrap();
+
+        let change = timeout(Duration::from_secs(10), events.recv())
+            .await
+            .expect("no change event")
+            .unwrap();
+        assert_eq!(change.path, "Bread.cook");
+        assert!(matches!(change.kind, ChangeKind::Created));
+        assert!(timeout(DEBOUNCE * 3, events.recv()).await.is_err());
+
+        // and the cached recipes were dropped
+        assert_eq!(state.recipes().unwrap().len(), 1);
+    }
+}
@@ -28,24 +28,36 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use anstream::ColorChoice;
-use anyhow::{bail, Context as _, Result};
+use anyhow::{Context as _, Result};
 use camino::Utf8PathBuf;
 use clap::{Args, ValueEnum};
-use std::collections::BTreeMap;
+use std::{borrow::Cow, collections::BTreeMap};
 use tracing::warn;
 use yansi::Paint;
 
 use cooklang::{
     aisle::AisleConf,
     ingredient_list::IngredientList,
-    quantity::{GroupedQuantity, Quantity, Value},
+    quantity::{GroupedQuantity, Quantity},
     ScaledQuantity,
 };
 use serde::Serialize;
 
 use crate::{
-    util::{extract_ingredients, write_to_output},
+    util::{
+        config_format,
+        cost::Costs,
+        db::Db,
+        extract_ingredients,
+        packages::{Packages, Rounded},
+        pantry::{Pantry, Subtracted},
+        parse_aisle,
+        plan::Plan,
+        sources::{Source, Sources},
+        split_recipe_name_and_scaling_factor,
+        units::Units,
+        write_to_output, RECIPE_SCALING_DELIMITER,
+    },
     Context,
 };
 
@@ -55,9 +67,20 @@ pub struct ShoppingListArgs {
     /// Recipe to add to the list
     ///
     /// Name or path to the file. It will use the default scaling of the recipe.
-    /// To use a custom scaling, add `@<scale>` at the end.
+    /// To use a custom scaling, add `:<scale>` at the end, or `:<servings>p`
+    /// to scale it to a number of servings.
     recipes: Vec<String>,
 
+    /// Scale every recipe without its own scaling to this number of servings
+    #[arg(long)]
+    servings: Option<u32>,
+
+    /// Meal plan file with more recipes to add
+    ///
+    /// See `cook plan show --help` for the format.
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    plan: Option<Utf8PathBuf>,
+
     /// Base path to search for recipes
     #[arg(short, long)]
     base_path: Option<Utf8PathBuf>,
@@ -72,7 +95,8 @@ pub struct ShoppingListArgs {
 
     /// Output format
     ///
-    /// Tries to infer it from output file extension. Defaults to "human".
+    /// Tries to infer it from output file extension. Defaults to
+    /// `shopping_list.format` from the config file or "human".
     #[arg(short, long, value_enum)]
     format: Option<OutputFormat>,
 
@@ -87,6 +111,38 @@ pub struct ShoppingListArgs {
     /// Don't expand referenced recipes
     #[arg(short, long)]
     ignore_references: bool,
+
+    /// Subtract what's already in the pantry
+    ///
+    /// Uses the given file or `pantry.conf` from the config dir. Only what's
+    /// missing is listed to buy, and what the pantry covers is listed apart.
+    #[arg(long, value_name = "FILE", require_equals = true)]
+    pantry: Option<Option<Utf8PathBuf>>,
+
+    /// Convert quantities to this unit system
+    #[arg(long, value_enum, default_value_t)]
+    units: Units,
+
+    /// Round quantities up to the packages stores sell
+    ///
+    /// Package sizes are read from `db/<ingredient>/shopping.yml`, like
+    /// `packages: [500 g, 1 kg]`. What will be left over is shown too.
+    #[arg(long)]
+    round_to_packages: bool,
+
+    /// Estimate the cost of every item, each category and the total
+    ///
+    /// Prices are read from `db/<ingredient>/shopping.yml`, with
+    /// `price_per_unit` and its `unit`. Items without a price are flagged.
+    #[arg(long)]
+    cost: bool,
+
+    /// Show which recipes need each item, and how much
+    ///
+    /// Includes the recipes added through references. CSV has them in a
+    /// `sources` column and todo.txt at the end of each task.
+    #[arg(long)]
+    sources: bool,
 }
 
 impl ShoppingListArgs {
@@ -99,7 +155,16 @@ impl ShoppingListArgs {
 enum OutputFormat {
     Human,
     Json,
+    #[value(alias("yml"))]
     Yaml,
+    /// Checklist grouped by aisle category
+    #[value(alias("md"))]
+    Markdown,
+    /// One row per quantity: category, name, quantity and unit
+    Csv,
+    /// A task per item, with the aisle category as context
+    #[value(alias("todo.txt"))]
+    Todo,
 }
 
 pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
@@ -113,26 +178,25 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
         .transpose()?;
 
     let aisle = if let Some((path, content)) = &aile_path {
-        match cooklang::aisle::parse(content) {
-            Ok(conf) => conf,
-            Err(e) => {
-                let stderr = std::io::stderr();
-                let color = anstream::AutoStream::choice(&stderr) != ColorChoice::Never;
-                cooklang::error::write_rich_error(&e, path.as_str(), content, color, stderr)?;
-                bail!("Error parsing aisle file")
-            }
-        }
+        parse_aisle(path, content)?
     } else {
         warn!("No aisle file found. Docs https://cooklang.org/docs/spec/#shopping-lists");
         Default::default()
     };
 
+    let default_format =
+        config_format(ctx.config().shopping_list.format.as_deref())?.unwrap_or(OutputFormat::Human);
+
     let format = args.format.unwrap_or_else(|| match &args.output {
         Some(p) => match p.extension() {
             Some("json") => OutputFormat::Json,
-            _ => OutputFormat::Human,
+            Some("yaml") | Some("yml") => OutputFormat::Yaml,
+            Some("md") => OutputFormat::Markdown,
+            Some("csv") => OutputFormat::Csv,
+            Some("txt") => OutputFormat::Todo,
+            _ => default_format,
         },
-        None => OutputFormat::Human,
+        None => default_format,
     });
 
     // retrieve, scale and merge ingredients
@@ -141,25 +205,83 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
 
     let ignore_references = args.ignore_references;
 
-    for entry in args.recipes {
+    let mut entries = args.recipes;
+    if let Some(servings) = args.servings {
+        for entry in &mut entries {
+            if split_recipe_name_and_scaling_factor(entry).is_none() {
+                *entry = format!("{entry}{RECIPE_SCALING_DELIMITER}{servings}p");
+            }
+        }
+    }
+    if let Some(path) = &args.plan {
+        let plan = Plan::load(path)?;
+        for recipe in plan.recipes() {
+            entries.push(recipe.shopping_list_entry());
+        }
+    }
+
+    let mut sources = args.sources.then(Sources::new);
+    for entry in entries {
         extract_ingredients(
             &entry,
             &mut list,
             &mut seen,
             ctx.base_path(),
-            ctx.parser()?.converter(),
+            ctx.parser()?,
             ignore_references,
+            sources.as_mut(),
         )?;
     }
 
+    let covered = match args.pantry {
+        Some(path) => {
+            let path = path.or_else(|| ctx.pantry()).context(
+                "No pantry file found. Create config/pantry.conf or use --pantry=<file>",
+            )?;
+            let pantry = Pantry::load(&path)?;
+            let Subtracted { to_buy, covered } = pantry.subtract(list, ctx.parser()?.converter());
+            list = to_buy;
+            Some(covered)
+        }
+        None => None,
+    };
+
+    let converter = ctx.parser()?.converter();
+    let list = args.units.convert_list(list, converter);
+    let covered = covered.map(|covered| args.units.convert_list(covered, converter));
+    if let Some(sources) = &mut sources {
+        sources.convert(args.units, converter);
+    }
+    let packages = args
+        .round_to_packages
+        .then(|| Packages::round(&list, &Db::new(ctx.base_path()), converter))
+        .transpose()?;
+    let costs = args
+        .cost
+        .then(|| {
+            let db = Db::new(ctx.base_path());
+            Costs::estimate(&list, packages.as_ref(), &db, converter)
+        })
+        .transpose()?;
+
+    let details = Details {
+        sources: sources.as_ref(),
+        packages: packages.as_ref(),
+        costs: costs.as_ref(),
+    };
+
     write_to_output(args.output.as_deref(), |mut w| {
         match format {
             OutputFormat::Human => {
-                let table = build_human_table(list, &aisle, args.plain);
+                let mut table = build_human_table(list, &aisle, args.plain, details);
+                if let Some(covered) = covered {
+                    add_pantry_rows(&mut table, covered, details.for_pantry());
+                }
                 write!(w, "{table}")?;
             }
             OutputFormat::Json => {
-                let value = build_json_value(list, &aisle, args.plain);
+                let value = build_json_value(list, &aisle, args.plain, details);
+                let value = with_extras(value, covered, &aisle, details);
                 if args.pretty {
                     serde_json::to_writer_pretty(w, &value)?;
                 } else {
@@ -167,22 +289,71 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
                 }
             }
             OutputFormat::Yaml => {
-                let value = build_yaml_value(list, &aisle);
-
+                let value = serde_json::to_value(categorized(list, &aisle, details))?;
+                let value = with_extras(value, covered, &aisle, details);
                 serde_yaml::to_writer(w, &value)?;
             }
+            OutputFormat::Markdown => {
+                write_markdown(w, list, covered, &aisle, args.plain, details)?
+            }
+            OutputFormat::Csv => write_csv(w, list, covered, &aisle, args.plain, details)?,
+            OutputFormat::Todo => write_todo(w, list, covered, &aisle, args.plain, details)?,
         }
         Ok(())
     })
 }
 
-fn total_quantity_fmt(qty: &GroupedQuantity, row: &mut tabular::Row) {
-    let content = qty
-        .iter()
-        .map(quantity_fmt)
-        .reduce(|s, q| format!("{s}, {q}"))
-        .unwrap_or_default();
-    row.add_ansi_cell(content);
+/// Extra info about the items, from the command options
+#[derive(Debug, Clone, Copy, Default)]
+struct Details<'a> {
+    sources: Option<&'a Sources>,
+    packages: Option<&'a Packages>,
+    costs: Option<&'a Costs>,
+}
+
+impl Details<'_> {
+    /// Without what only applies to the items to buy
+    fn for_pantry(self) -> Self {
+        Self {
+            packages: None,
+            costs: None,
+            ..self
+        }
+    }
+
+    /// What to buy of an ingredient, rounded to packages if possible, and a
+    /// note about it
+    fn amount(&self, name: &str, qty: &GroupedQuantity) -> (String, Option<String>) {
+        let Some(rounded) = self.packages.and_then(|p| p.get(name)) else {
+            let quantities = qty.iter().map(quantity_fmt).collect::<Vec<_>>();
+            return (quantities.join(", "), None);
+        };
+
+        let amounts = rounded
+            .packages
+            .iter()
+            .map(|p| format!("{} × {}", p.count, quantity_fmt(&p.size)))
+            .chain(rounded.rest.iter().map(quantity_fmt))
+            .collect::<Vec<_>>();
+        let leftovers = rounded
+            .packages
+            .iter()
+            .filter(|p| p.has_leftover())
+            .map(|p| quantity_fmt(&p.leftover))
+            .collect::<Vec<_>>();
+        let note = (!leftovers.is_empty()).then(|| format!("{} left over", leftovers.join(", ")));
+        (amounts.join(", "), note)
+    }
+
+    /// Cost of an item, if costs are estimated
+    fn cost(&self, name: &str) -> Option<String> {
+        let costs = self.costs?;
+        Some(costs.get(name).map_or("no price".into(), price_fmt))
+    }
+}
+
+fn price_fmt(price: f64) -> String {
+    format!("{price:.2}")
 }
 
 fn quantity_fmt(qty: &Quantity) -> String {
@@ -193,121 +364,718 @@ fn quantity_fmt(qty: &Quantity) -> String {
     }
 }
 
-fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> tabular::Table {
-    let mut table = tabular::Table::new("{:<} {:<}");
+fn item_row(name: &str, qty: &GroupedQuantity, details: Details) -> tabular::Row {
+    let (amount, note) = details.amount(name, qty);
+    let amount = match note {
+        Some(note) => format!("{amount} {}", format!("({note})").dim()),
+        None => amount,
+    };
+    let mut row = tabular::Row::new().with_cell(name).with_ansi_cell(amount);
+    if let Some(costs) = details.costs {
+        match costs.get(name) {
+            Some(cost) => row.add_cell(price_fmt(cost)),
+            None => row.add_ansi_cell("no price".yellow()),
+        };
+    }
+    row
+}
+
+/// Adds empty cells for the columns of the table the row doesn't use
+fn pad_row(mut row: tabular::Row, details: Details) -> tabular::Row {
+    if details.costs.is_some() {
+        row.add_cell("");
+    }
+    row
+}
+
+fn add_cost_row(table: &mut tabular::Table, label: &str, cost: f64, bold: bool) {
+    let cost = price_fmt(cost);
+    let (label, cost) = if bold {
+        (label.bold(), cost.bold())
+    } else {
+        (label.dim(), cost.dim())
+    };
+    table.add_row(
+        tabular::Row::new()
+            .with_ansi_cell(label)
+            .with_cell("")
+            .with_ansi_cell(cost),
+    );
+}
+
+fn build_human_table(
+    list: IngredientList,
+    aisle: &AisleConf,
+    plain: bool,
+    details: Details,
+) -> tabular::Table {
+    let mut table = if details.costs.is_some() {
+        tabular::Table::new("{:<} {:<}  {:>}")
+    } else {
+        tabular::Table::new("{:<} {:<}")
+    };
     if plain {
         for (igr, q) in list {
-            let mut row = tabular::Row::new().with_cell(igr);
-            total_quantity_fmt(&q, &mut row);
-            table.add_row(row);
+            table.add_row(item_row(&igr, &q, details));
+            add_source_rows(&mut table, &igr, details);
         }
     } else {
         let categories = list.categorize(aisle);
         for (cat, items) in categories {
             table.add_heading(format!("[{}]", cat.green()));
+            let mut subtotal = 0.0;
             for (igr, q) in items {
-                let mut row = tabular::Row::new().with_cell(igr);
-                total_quantity_fmt(&q, &mut row);
-                table.add_row(row);
+                table.add_row(item_row(&igr, &q, details));
+                add_source_rows(&mut table, &igr, details);
+                subtotal += details.costs.and_then(|c| c.get(&igr)).unwrap_or_default();
+            }
+            if details.costs.is_some() {
+                add_cost_row(&mut table, "  subtotal", subtotal, false);
             }
         }
     }
+    if let Some(costs) = details.costs {
+        add_cost_row(&mut table, "total", costs.total(), true);
+        let missing = costs.missing().collect::<Vec<_>>();
+        if !missing.is_empty() {
+            table.add_heading(format!(
+                "{} {}",
+                "no price for:".yellow(),
+                missing.join(", ")
+            ));
+        }
+    }
     table
 }
 
-fn build_json_value<'a>(
+fn add_pantry_rows(table: &mut tabular::Table, covered: IngredientList, details: Details) {
+    if covered.is_empty() {
+        return;
+    }
+    table.add_heading(format!("[{}]", "in pantry".blue()));
+    for (igr, q) in covered {
+        let (amount, _) = details.for_pantry().amount(&igr, &q);
+        let row = tabular::Row::new()
+            .with_ansi_cell(igr.as_str().dim())
+            .with_ansi_cell(amount);
+        table.add_row(pad_row(row, details));
+        add_source_rows(table, &igr, details);
+    }
+}
+
+fn add_source_rows(table: &mut tabular::Table, ingredient: &str, details: Details) {
+    let Some(sources) = details.sources else {
+        return;
+    };
+    for source in sources.get(ingredient) {
+        let row = tabular::Row::new()
+            .with_ansi_cell(format!("  {}", source.recipe).dim())
+            .with_ansi_cell(source_quantity_fmt(source).dim());
+        table.add_row(pad_row(row, details));
+    }
+}
+
+fn source_quantity_fmt(source: &Source) -> String {
+    source
+        .quantity
+        .iter()
+        .map(quantity_fmt)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Serialize)]
+struct Ingredient {
+    name: String,
+    quantity: Vec<ScaledQuantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<Source>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    packages: Option<Rounded>,
+    /// `null` when the cost is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost: Option<Option<f64>>,
+}
+
+impl Ingredient {
+    fn new((name, qty): (String, GroupedQuantity), details: Details) -> Self {
+        Ingredient {
+            sources: details.sources.map(|s| s.get(&name).to_vec()),
+            packages: details.packages.and_then(|p| p.get(&name)).cloned(),
+            cost: details.costs.map(|c| c.get(&name)),
+            name,
+            quantity: qty.into_vec(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Category {
+    category: String,
+    items: Vec<Ingredient>,
+    /// Known cost of the items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost: Option<f64>,
+}
+
+fn categorized(list: IngredientList, aisle: &AisleConf, details: Details) -> Vec<Category> {
+    list.categorize(aisle)
+        .into_iter()
+        .map(|(category, items)| {
+            let items = items
+                .into_iter()
+                .map(|item| Ingredient::new(item, details))
+                .collect::<Vec<_>>();
+            Category {
+                category,
+                cost: details.costs.map(|_| {
+                    items
+                        .iter()
+                        .filter_map(|i| i.cost.flatten())
+                        .fold(0.0, |a, b| a + b)
+                }),
+                items,
+            }
+        })
+        .collect()
+}
+
+fn build_json_value(
     list: IngredientList,
-    aisle: &'a AisleConf<'a>,
+    aisle: &AisleConf,
     plain: bool,
+    details: Details,
 ) -> serde_json::Value {
-    #[derive(Serialize)]
-    struct Quantity {
-        value: Value,
-        unit: Option<String>,
-    }
-    impl From<cooklang::quantity::Quantity> for Quantity {
-        fn from(qty: cooklang::quantity::Quantity) -> Self {
-            let unit = qty.unit().map(|s| s.to_owned());
-            let value = qty.value().clone();
-            Self { value, unit }
-        }
+    if plain {
+        let items = list
+            .into_iter()
+            .map(|item| Ingredient::new(item, details))
+            .collect::<Vec<_>>();
+        serde_json::to_value(items).unwrap()
+    } else {
+        serde_json::to_value(categorized(list, aisle, details)).unwrap()
     }
-    #[derive(Serialize)]
-    struct Ingredient {
-        name: String,
-        quantity: Vec<ScaledQuantity>,
+}
+
+/// Adds what the pantry covers and the total cost, if any, around the items
+fn with_extras(
+    items: serde_json::Value,
+    covered: Option<IngredientList>,
+    aisle: &AisleConf,
+    details: Details,
+) -> serde_json::Value {
+    if covered.is_none() && details.costs.is_none() {
+        return items;
     }
-    impl From<(String, GroupedQuantity)> for Ingredient {
-        fn from((name, qty): (String, GroupedQuantity)) -> Self {
-            Ingredient {
-                name,
-                quantity: qty.into_vec(),
-            }
-        }
+    let mut value = serde_json::json!({ "items": items });
+    if let Some(covered) = covered {
+        value["pantry"] = build_json_value(covered, aisle, true, details.for_pantry());
     }
-    #[derive(Serialize)]
-    struct Category {
-        category: String,
-        items: Vec<Ingredient>,
+    if let Some(costs) = details.costs {
+        value["cost"] = serde_json::json!({
+            "total": costs.total(),
+            "missing": costs.missing().collect::<Vec<_>>(),
+        });
     }
+    value
+}
 
+/// Groups of `(category, items)`, a single unnamed one when `plain`
+fn groups(
+    list: IngredientList,
+    aisle: &AisleConf,
+    plain: bool,
+) -> Vec<(Option<String>, IngredientList)> {
     if plain {
-        serde_json::to_value(list.into_iter().map(Ingredient::from).collect::<Vec<_>>()).unwrap()
+        vec![(None, list)]
     } else {
-        serde_json::to_value(
-            list.categorize(aisle)
-                .into_iter()
-                .map(|(category, items)| Category {
-                    category,
-                    items: items.into_iter().map(Ingredient::from).collect(),
-                })
-                .collect::<Vec<_>>(),
-        )
-        .unwrap()
+        list.categorize(aisle)
+            .into_iter()
+            .map(|(category, items)| (Some(category), items))
+            .collect()
+    }
+}
+
+/// Text of an item for the todo formats, like `200 g, 1 cup flour`
+fn item_text(name: &str, qty: &GroupedQuantity, details: Details) -> String {
+    let (amount, note) = details.amount(name, qty);
+    let mut text = if amount.is_empty() {
+        name.to_string()
+    } else {
+        format!("{amount} {name}")
+    };
+    if let Some(note) = note {
+        text += &format!(" ({note})");
     }
+    if let Some(cost) = details.cost(name) {
+        text += &format!(" — {cost}");
+    }
+    text
 }
 
-fn build_yaml_value<'a>(list: IngredientList, aisle: &'a AisleConf<'a>) -> serde_yaml::Value {
-    #[derive(Serialize)]
-    struct Quantity {
-        value: Value,
-        unit: Option<String>,
-    }
-    impl From<cooklang::quantity::Quantity> for Quantity {
-        fn from(qty: cooklang::quantity::Quantity) -> Self {
-            let unit = qty.unit().map(|s| s.to_owned());
-            let value = qty.value().clone();
-            Self { value, unit }
+fn write_markdown(
+    mut w: impl std::io::Write,
+    list: IngredientList,
+    covered: Option<IngredientList>,
+    aisle: &AisleConf,
+    plain: bool,
+    details: Details,
+) -> Result<()> {
+    let mut first = true;
+    let mut section = |w: &mut dyn std::io::Write, heading: Option<&str>| -> Result<()> {
+        if !first {
+            writeln!(w)?;
+        }
+        first = false;
+        if let Some(heading) = heading {
+            writeln!(w, "## {heading}\n")?;
+        }
+        Ok(())
+    };
+
+    for (category, items) in groups(list, aisle, plain) {
+        section(&mut w, category.as_deref())?;
+        for (name, qty) in items {
+            writeln!(w, "- [ ] {}", item_text(&name, &qty, details))?;
+            write_markdown_sources(&mut w, &name, details)?;
+        }
+    }
+    if let Some(covered) = covered.filter(|c| !c.is_empty()) {
+        let details = details.for_pantry();
+        section(&mut w, Some("In pantry"))?;
+        for (name, qty) in covered {
+            writeln!(w, "- [x] {}", item_text(&name, &qty, details))?;
+            write_markdown_sources(&mut w, &name, details)?;
         }
     }
-    #[derive(Serialize)]
-    struct Ingredient {
-        name: String,
-        quantity: Vec<ScaledQuantity>,
+    if let Some(costs) = details.costs {
+        writeln!(w, "\n**Total: {}**", price_fmt(costs.total()))?;
+        let missing = costs.missing().collect::<Vec<_>>();
+        if !missing.is_empty() {
+            writeln!(w, "\nNo price for: {}", missing.join(", "))?;
+        }
     }
-    impl From<(String, GroupedQuantity)> for Ingredient {
-        fn from((name, qty): (String, GroupedQuantity)) -> Self {
-            Ingredient {
-                name,
-                quantity: qty.into_vec(),
+    Ok(())
+}
+
+fn write_markdown_sources(
+    mut w: impl std::io::Write,
+    ingredient: &str,
+    details: Details,
+) -> Result<()> {
+    for source in details.sources.map_or(&[][..], |s| s.get(ingredient)) {
+        match source_quantity_fmt(source) {
+            q if q.is_empty() => writeln!(w, "  - {}", source.recipe)?,
+            q => writeln!(w, "  - {}: {q}", source.recipe)?,
+        }
+    }
+    Ok(())
+}
+
+/// Recipes that need an item, like `Pasta.cook: 200 g; Pizza.cook`, for the
+/// formats without nested lines
+fn sources_text(ingredient: &str, details: Details) -> Option<String> {
+    let sources = details.sources?.get(ingredient);
+    if sources.is_empty() {
+        return None;
+    }
+    let text = sources
+        .iter()
+        .map(|source| match source_quantity_fmt(source) {
+            q if q.is_empty() => source.recipe.clone(),
+            q => format!("{}: {q}", source.recipe),
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Some(text)
+}
+
+fn write_csv(
+    mut w: impl std::io::Write,
+    list: IngredientList,
+    covered: Option<IngredientList>,
+    aisle: &AisleConf,
+    plain: bool,
+    details: Details,
+) -> Result<()> {
+    fn field(s: &str) -> Cow<'_, str> {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\"")).into()
+        } else {
+            s.into()
+        }
+    }
+
+    let mut rows = groups(list, aisle, plain)
+        .into_iter()
+        .map(|(category, items)| (category, items, details))
+        .collect::<Vec<_>>();
+    if let Some(covered) = covered {
+        rows.push((Some("in pantry".to_string()), covered, details.for_pantry()));
+    }
+
+    // extra columns for the options, the cost only in the first row of an
+    // item so it can be added up
+    let rounding = details.packages.is_some();
+    let listing_sources = details.sources.is_some();
+    let costing = details.costs.is_some();
+    let mut header = String::from("category,name,quantity,unit");
+    if rounding {
+        header += ",packages";
+    }
+    if listing_sources {
+        header += ",sources";
+    }
+    if costing {
+        header += ",cost";
+    }
+    writeln!(w, "{header}")?;
+    for (category, items, details) in rows {
+        let category = category.unwrap_or_default();
+        for (name, qty) in items {
+            let mut suffix = String::new();
+            if rounding {
+                let packages = details.packages.and_then(|p| p.get(&name)).map(|_| {
+                    let (amount, note) = details.amount(&name, &qty);
+                    note.map_or(amount.clone(), |note| format!("{amount} ({note})"))
+                });
+                suffix = format!(",{}", field(&packages.unwrap_or_default()));
+            }
+            if listing_sources {
+                let sources = sources_text(&name, details).unwrap_or_default();
+                suffix += &format!(",{}", field(&sources));
+            }
+            let mut cost = costing.then(|| {
+                let cost = details.costs.and_then(|c| c.get(&name));
+                format!(",{}", cost.map(price_fmt).unwrap_or_default())
+            });
+
+            let prefix = format!("{},{}", field(&category), field(&name));
+            if qty.is_empty() {
+                let cost = cost.take().unwrap_or_default();
+                writeln!(w, "{prefix},,{suffix}{cost}")?;
+            }
+            for q in qty.iter() {
+                let value = q.value().to_string();
+                let unit = q.unit().unwrap_or_default();
+                let cost = match cost.take() {
+                    Some(cost) => cost,
+                    None if costing => ",".to_string(),
+                    None => String::new(),
+                };
+                writeln!(
+                    w,
+                    "{prefix},{},{}{suffix}{cost}",
+                    field(&value),
+                    field(unit)
+                )?;
             }
         }
     }
-    #[derive(Serialize)]
-    struct Category {
-        category: String,
-        items: Vec<Ingredient>,
+    Ok(())
+}
+
+fn write_todo(
+    mut w: impl std::io::Write,
+    list: IngredientList,
+    covered: Option<IngredientList>,
+    aisle: &AisleConf,
+    plain: bool,
+    details: Details,
+) -> Result<()> {
+    // contexts are single words, like `@packaged_goods_pasta_and_sauces`
+    fn context(category: &str) -> String {
+        let category = category
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+        format!(" @{category}")
     }
 
-    // Convert to categorized list and serialize to YAML
-    serde_yaml::to_value(
-        list.categorize(aisle)
-            .into_iter()
-            .map(|(category, items)| Category {
-                category,
-                items: items.into_iter().map(Ingredient::from).collect(),
-            })
-            .collect::<Vec<_>>(),
-    )
-    .unwrap()
+    // a task is a single line
+    fn todo_sources(name: &str, details: Details) -> String {
+        sources_text(name, details)
+            .map(|s| format!(" [{s}]"))
+            .unwrap_or_default()
+    }
+
+    for (category, items) in groups(list, aisle, plain) {
+        let context = category.as_deref().map(context).unwrap_or_default();
+        for (name, qty) in items {
+            let sources = todo_sources(&name, details);
+            writeln!(w, "{}{sources}{context}", item_text(&name, &qty, details))?;
+        }
+    }
+    // already done
+    let details = details.for_pantry();
+    for (name, qty) in covered.into_iter().flatten() {
+        let sources = todo_sources(&name, details);
+        writeln!(w, "x {}{sources}", item_text(&name, &qty, details))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::CooklangParser;
+
+    use super::*;
+    use crate::util::{parse_aisle, parse_recipe, Scaling};
+
+    const RECIPE: &str = "\
+Mix @flour{500%g}, @salt, coarse{1%tsp}, @5\" tortillas{4} and @milk{200%ml}.
+Add more @flour{100%g} and @pepper.";
+
+    const AISLE: &str = "[Baking]\nflour\n[Dairy & Eggs]\nmilk\n";
+
+    fn list(content: &str) -> IngredientList {
+        let parser = CooklangParser::canonical();
+        let recipe = parse_recipe(content, &parser, Scaling::Factor(1.0)).unwrap();
+        let mut list = IngredientList::new();
+        list.add_recipe(&recipe, parser.converter(), false);
+        list
+    }
+
+    fn aisle() -> AisleConf<'static> {
+        parse_aisle("aisle.conf".into(), AISLE).unwrap()
+    }
+
+    fn written(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn pantry() -> Option<IngredientList> {
+        Some(list("Use @milk{1%l}."))
+    }
+
+    #[test]
+    fn markdown() {
+        let out = written(|w| {
+            write_markdown(
+                w,
+                list(RECIPE),
+                pantry(),
+                &aisle(),
+                false,
+                Details::default(),
+            )
+        });
+        assert_eq!(
+            out,
+            "\
+## Baking
+
+- [ ] 600 g flour
+
+## Dairy & Eggs
+
+- [ ] 200 ml milk
+
+## other
+
+- [ ] 4 5\" tortillas
+- [ ] pepper
+- [ ] 1 tsp salt, coarse
+
+## In pantry
+
+- [x] 1 l milk
+"
+        );
+
+        let out =
+            written(|w| write_markdown(w, list(RECIPE), None, &aisle(), true, Details::default()));
+        assert_eq!(
+            out,
+            "\
+- [ ] 4 5\" tortillas
+- [ ] 600 g flour
+- [ ] 200 ml milk
+- [ ] pepper
+- [ ] 1 tsp salt, coarse
+"
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let out = written(|w| {
+            write_csv(
+                w,
+                list(RECIPE),
+                pantry(),
+                &aisle(),
+                false,
+                Details::default(),
+            )
+        });
+        assert_eq!(
+            out,
+            "\
+category,name,quantity,unit
+Baking,flour,600,g
+Dairy & Eggs,milk,200,ml
+other,\"5\"\" tortillas\",4,
+other,pepper,,
+other,\"salt, coarse\",1,tsp
+in pantry,milk,1,l
+"
+        );
+    }
+
+    #[test]
+    fn todo() {
+        let out = written(|w| {
+            write_todo(
+                w,
+                list(RECIPE),
+                pantry(),
+                &aisle(),
+                false,
+                Details::default(),
+            )
+        });
+        assert_eq!(
+            out,
+            "\
+600 g flour @Baking
+200 ml milk @Dairy_Eggs
+4 5\" tortillas @other
+pepper @other
+1 tsp salt, coarse @other
+x 1 l milk
+"
+        );
+    }
+
+    /// List and sources of `entries`, a recipe with a reference and another
+    fn with_sources(entries: &[&str]) -> (IngredientList, Sources) {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        for (name, content) in [
+            ("Pizza", "Top @./Dough{} with @tomato{200%g}."),
+            ("Dough", "Knead @flour{300%g} and @salt{1%tsp}."),
+            (
+                "Pasta",
+                "Cook @pasta{200%g} with @tomato{100%g} and @salt{2%tsp}.",
+            ),
+        ] {
+            std::fs::write(base_path.join(format!("{name}.cook")), content).unwrap();
+        }
+
+        let parser = CooklangParser::canonical();
+        let mut list = IngredientList::new();
+        let mut sources = Sources::new();
+        for entry in entries {
+            extract_ingredients(
+                entry,
+                &mut list,
+                &mut BTreeMap::new(),
+                &base_path,
+                &parser,
+                false,
+                Some(&mut sources),
+            )
+            .unwrap();
+        }
+        (list, sources)
+    }
+
+    /// Output of a writer for the shopping list of a pizza and double pasta,
+    /// with the sources
+    fn sources_output(write: fn(&mut Vec<u8>, IngredientList, Details) -> Result<()>) -> String {
+        let (list, sources) = with_sources(&["Pizza", "Pasta:2"]);
+        let details = Details {
+            sources: Some(&sources),
+            ..Default::default()
+        };
+        written(|w| write(w, list, details))
+    }
+
+    #[test]
+    fn sources() {
+        let human = sources_output(|w, list, details| {
+            let table = build_human_table(list, &aisle(), true, details);
+            w.extend(table.to_string().into_bytes());
+            Ok(())
+        });
+        let ansi = regex::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+        assert_eq!(
+            ansi.replace_all(&human, ""),
+            "\
+flour        300 g
+  Dough.cook 300 g
+pasta        400 g
+  Pasta.cook 400 g
+salt         5 tsp
+  Dough.cook 1 tsp
+  Pasta.cook 4 tsp
+tomato       400 g
+  Pizza.cook 200 g
+  Pasta.cook 200 g
+"
+        );
+
+        let markdown = sources_output(|w, list, details| {
+            write_markdown(w, list, None, &aisle(), true, details)
+        });
+        assert_eq!(
+            markdown,
+            "\
+- [ ] 300 g flour
+  - Dough.cook: 300 g
+- [ ] 400 g pasta
+  - Pasta.cook: 400 g
+- [ ] 5 tsp salt
+  - Dough.cook: 1 tsp
+  - Pasta.cook: 4 tsp
+- [ ] 400 g tomato
+  - Pizza.cook: 200 g
+  - Pasta.cook: 200 g
+"
+        );
+
+        let csv =
+            sources_output(|w, list, details| write_csv(w, list, None, &aisle(), true, details));
+        assert_eq!(
+            csv,
+            "\
+category,name,quantity,unit,sources
+,flour,300,g,Dough.cook: 300 g
+,pasta,400,g,Pasta.cook: 400 g
+,salt,5,tsp,Dough.cook: 1 tsp; Pasta.cook: 4 tsp
+,tomato,400,g,Pizza.cook: 200 g; Pasta.cook: 200 g
+"
+        );
+
+        let todo =
+            sources_output(|w, list, details| write_todo(w, list, None, &aisle(), true, details));
+        assert_eq!(
+            todo,
+            "\
+300 g flour [Dough.cook: 300 g]
+400 g pasta [Pasta.cook: 400 g]
+5 tsp salt [Dough.cook: 1 tsp; Pasta.cook: 4 tsp]
+400 g tomato [Pizza.cook: 200 g; Pasta.cook: 200 g]
+"
+        );
+
+        let json = sources_output(|w, list, details| {
+            serde_json::to_writer(w, &build_json_value(list, &aisle(), true, details))?;
+            Ok(())
+        });
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let salt = &json[2];
+        assert_eq!(salt["name"], "salt");
+        assert_eq!(salt["sources"][0]["recipe"], "Dough.cook");
+        assert_eq!(salt["sources"][0]["quantity"][0]["unit"], "tsp");
+        assert_eq!(salt["sources"][1]["recipe"], "Pasta.cook");
+        assert_eq!(
+            salt["sources"][1]["quantity"][0]["value"]["value"]["value"],
+            4.0
+        );
+    }
 }
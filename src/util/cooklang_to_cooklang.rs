@@ -30,36 +30,89 @@
 
 //! Format a recipe as cooklang
 
-use std::{fmt::Write, io};
+use std::{collections::HashMap, fmt::Write, io};
 
 use anyhow::{Context, Result};
 use cooklang::{
     metadata::Metadata,
     model::{Item, Section, Step},
     parser::Modifiers,
-    quantity::{Quantity, QuantityValue},
+    quantity::{Quantity, QuantityValue, ScalableValue, Value},
     Recipe,
 };
 use regex::Regex;
 
-pub fn print_cooklang<D, V: QuantityValue>(
+/// Width used by [`print_cooklang`], capped to the terminal width
+pub fn default_width() -> usize {
+    textwrap::termwidth().min(80)
+}
+
+pub fn print_cooklang<D, V: QuantityValue + ComponentValue>(
     recipe: &Recipe<D, V>,
+    writer: impl io::Write,
+) -> Result<()> {
+    print_cooklang_with_width(recipe, default_width(), &Comments::default(), writer)
+}
+
+/// Same as [`print_cooklang`] but wrapping steps at a fixed `width`, so the
+/// output does not depend on the terminal, and writing back `comments`.
+pub fn print_cooklang_with_width<D, V: QuantityValue + ComponentValue>(
+    recipe: &Recipe<D, V>,
+    width: usize,
+    comments: &Comments,
     mut writer: impl io::Write,
 ) -> Result<()> {
     let w = &mut writer;
 
-    metadata(w, &recipe.metadata).context("Failed to write metadata")?;
-    writeln!(w).context("Failed to write newline")?;
-    sections(w, recipe).context("Failed to write sections")?;
+    let has_metadata = metadata(w, &recipe.metadata).context("Failed to write metadata")?;
+    if has_metadata && (recipe.sections.iter().any(|s| !s.is_empty()) || !comments.after.is_empty())
+    {
+        writeln!(w).context("Failed to write newline")?;
+    }
+    sections(w, recipe, width, comments).context("Failed to write sections")?;
+    for comment in &comments.after {
+        writeln!(w, "{comment}").context("Failed to write comment")?;
+    }
 
     Ok(())
 }
 
-fn metadata(w: &mut impl io::Write, metadata: &Metadata) -> Result<()> {
+/// Comments to write back into a recipe, as the parser drops them.
+///
+/// Contents are numbered in order across all sections.
+#[derive(Debug, Default)]
+pub struct Comments {
+    /// Contents with comments inside, written as they are in the source
+    pub verbatim: HashMap<usize, String>,
+    /// Comments before the section header that precedes a content
+    pub before_header: HashMap<usize, Vec<String>>,
+    /// Comments right before a content
+    pub before_content: HashMap<usize, Vec<String>>,
+    /// Comments after the last content
+    pub after: Vec<String>,
+}
+
+/// Quantity values that can be written back inside a component
+pub trait ComponentValue: QuantityValue {
+    /// Value marked as fixed with `=`, so it does not scale
+    fn is_fixed(&self) -> bool {
+        false
+    }
+}
+
+impl ComponentValue for Value {}
+
+impl ComponentValue for ScalableValue {
+    fn is_fixed(&self) -> bool {
+        matches!(self, ScalableValue::Fixed(_))
+    }
+}
+
+fn metadata(w: &mut impl io::Write, metadata: &Metadata) -> Result<bool> {
     // TODO if the recipe has been scaled and multiple servings are defined
     // it can lead to the recipe not parsing.
     if metadata.map.is_empty() {
-        return Ok(());
+        return Ok(false);
     }
 
     let map = metadata.map.clone();
@@ -67,46 +120,82 @@ fn metadata(w: &mut impl io::Write, metadata: &Metadata) -> Result<()> {
     const FRONTMATTER_FENCE: &str = "---";
     writeln!(w, "{}", FRONTMATTER_FENCE).context("Failed to write frontmatter start")?;
     serde_yaml::to_writer(&mut *w, &map).context("Failed to serialize frontmatter")?;
-    writeln!(w, "{}\n", FRONTMATTER_FENCE).context("Failed to write frontmatter end")?;
-    Ok(())
+    writeln!(w, "{}", FRONTMATTER_FENCE).context("Failed to write frontmatter end")?;
+    Ok(true)
 }
 
-fn sections<D, V: QuantityValue>(w: &mut impl io::Write, recipe: &Recipe<D, V>) -> Result<()> {
+fn sections<D, V: QuantityValue + ComponentValue>(
+    w: &mut impl io::Write,
+    recipe: &Recipe<D, V>,
+    width: usize,
+    comments: &Comments,
+) -> Result<()> {
+    let mut first = true;
+    let mut content_index = 0;
     for (index, section) in recipe.sections.iter().enumerate() {
-        w_section(w, section, recipe, index).context("Failed to write section")?;
+        if section.is_empty() {
+            continue;
+        }
+        if !first {
+            writeln!(w).context("Failed to write newline")?;
+        }
+        first = false;
+        w_section(w, section, recipe, index, content_index, width, comments)
+            .context("Failed to write section")?;
+        content_index += section.content.len();
+    }
+    Ok(())
+}
+
+fn w_comments(w: &mut impl io::Write, comments: Option<&Vec<String>>) -> Result<()> {
+    for comment in comments.into_iter().flatten() {
+        writeln!(w, "{comment}").context("Failed to write comment")?;
     }
     Ok(())
 }
 
-fn w_section<D, V: QuantityValue>(
+fn w_section<D, V: QuantityValue + ComponentValue>(
     w: &mut impl io::Write,
     section: &Section,
     recipe: &Recipe<D, V>,
     index: usize,
+    content_index: usize,
+    width: usize,
+    comments: &Comments,
 ) -> Result<()> {
+    w_comments(w, comments.before_header.get(&content_index))?;
     if let Some(name) = &section.name {
         writeln!(w, "== {name} ==").context("Failed to write section name")?;
     } else if index > 0 {
         writeln!(w, "====").context("Failed to write section separator")?;
     }
-    for content in &section.content {
+    for (index, content) in section.content.iter().enumerate() {
+        if index > 0 {
+            writeln!(w).context("Failed to write newline")?;
+        }
+        let content_index = content_index + index;
+        w_comments(w, comments.before_content.get(&content_index))?;
+        if let Some(source) = comments.verbatim.get(&content_index) {
+            writeln!(w, "{source}").context("Failed to write content")?;
+            continue;
+        }
         match content {
             cooklang::Content::Step(step) => {
-                w_step(w, step, recipe).context("Failed to write step")?
+                w_step(w, step, recipe, width).context("Failed to write step")?
             }
             cooklang::Content::Text(text) => {
-                w_text_block(w, text).context("Failed to write text block")?
+                w_text_block(w, text, width).context("Failed to write text block")?
             }
         }
-        writeln!(w).context("Failed to write newline")?;
     }
     Ok(())
 }
 
-fn w_step<D, V: QuantityValue>(
+fn w_step<D, V: QuantityValue + ComponentValue>(
     w: &mut impl io::Write,
     step: &Step,
     recipe: &Recipe<D, V>,
+    width: usize,
 ) -> Result<()> {
     let mut step_str = String::new();
     for item in &step.items {
@@ -160,12 +249,11 @@ fn w_step<D, V: QuantityValue>(
                 write!(&mut step_str, "{}", q.value())
                     .context("Failed to write inline quantity")?;
                 if let Some(u) = q.unit() {
-                    step_str.push_str(u);
+                    write!(&mut step_str, " {u}").context("Failed to write inline quantity")?;
                 }
             }
         }
     }
-    let width = textwrap::termwidth().min(80);
     let options = textwrap::Options::new(width)
         .word_separator(textwrap::WordSeparator::Custom(component_word_separator));
     let lines = textwrap::wrap(step_str.trim(), options);
@@ -175,8 +263,7 @@ fn w_step<D, V: QuantityValue>(
     Ok(())
 }
 
-fn w_text_block(w: &mut impl io::Write, text: &str) -> Result<()> {
-    let width = textwrap::termwidth().min(80);
+fn w_text_block(w: &mut impl io::Write, text: &str, width: usize) -> Result<()> {
     let indent = "> ";
     let options = textwrap::Options::new(width)
         .initial_indent(indent)
@@ -189,7 +276,8 @@ fn w_text_block(w: &mut impl io::Write, text: &str) -> Result<()> {
 }
 
 // This prevents spliting a multi word component in two lines, because that's
-// invalid.
+// invalid. Lines are only broken at spaces, as a line break becomes a space
+// when the recipe is parsed again.
 fn component_word_separator<'a>(
     line: &'a str,
 ) -> Box<dyn Iterator<Item = textwrap::core::Word<'a>> + 'a> {
@@ -202,14 +290,32 @@ fn component_word_separator<'a>(
 
     let mut words = vec![];
     let mut last_added = 0;
-    let default_separator = textwrap::WordSeparator::new();
+    let default_separator = textwrap::WordSeparator::AsciiSpace;
 
-    for component in re.find_iter(line) {
-        if last_added < component.start() {
-            words.extend(default_separator.find_words(&line[last_added..component.start()]));
+    let mut components = re.find_iter(line).peekable();
+    while let Some(component) = components.next() {
+        // glue the component to any text around it up to the next space
+        let start = line[last_added..component.start()]
+            .rfind(' ')
+            .map_or(last_added, |i| last_added + i + 1);
+        let mut end = component.end();
+        loop {
+            end = line[end..].find(' ').map_or(line.len(), |i| end + i);
+            match components.peek() {
+                Some(next) if next.start() < end => {
+                    end = end.max(next.end());
+                    components.next();
+                }
+                _ => break,
+            }
+        }
+        end = line.len() - line[end..].trim_start_matches(' ').len();
+
+        if last_added < start {
+            words.extend(default_separator.find_words(&line[last_added..start]));
         }
-        words.push(Word::from(&line[component.range()]));
-        last_added = component.end();
+        words.push(Word::from(&line[start..end]));
+        last_added = end;
     }
     if last_added < line.len() {
         words.extend(default_separator.find_words(&line[last_added..]));
@@ -217,7 +323,7 @@ fn component_word_separator<'a>(
     Box::new(words.into_iter())
 }
 
-struct ComponentFormatter<'a, V: QuantityValue> {
+struct ComponentFormatter<'a, V: ComponentValue> {
     kind: ComponentKind,
     modifiers: Modifiers,
     name: Option<&'a str>,
@@ -232,7 +338,7 @@ enum ComponentKind {
     Timer,
 }
 
-impl<V: QuantityValue> ComponentFormatter<'_, V> {
+impl<V: ComponentValue> ComponentFormatter<'_, V> {
     fn format(self, w: &mut String) {
         w.push(match self.kind {
             ComponentKind::Ingredient => '@',
@@ -263,6 +369,10 @@ impl<V: QuantityValue> ComponentFormatter<'_, V> {
         }
         if let Some(q) = self.quantity {
             w.push('{');
+            // timers are never scaled, the marker is implied
+            if q.value().is_fixed() && !matches!(self.kind, ComponentKind::Timer) {
+                w.push('=');
+            }
             w.push_str(&q.value().to_string());
             if let Some(unit) = q.unit() {
                 write!(w, "%{}", unit).unwrap();
@@ -0,0 +1,428 @@
+//! Format a recipe as a standalone HTML page
+
+use std::{borrow::Cow, fmt::Write, io, time::Duration};
+
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use camino::Utf8Path;
+use cooklang::{
+    convert::Converter,
+    metadata::{CooklangValueExt, NameAndUrl, RecipeTime},
+    model::{Item, Section, Step},
+    ScaledRecipe,
+};
+use html_escape::{encode_double_quoted_attribute as attr, encode_text as text};
+
+const STYLE: &str = r#"
+body { font-family: Georgia, serif; color: #222; max-width: 46rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+h1 { margin-bottom: 0.25rem; }
+h2 { border-bottom: 1px solid #ccc; padding-bottom: 0.2rem; }
+.tags { list-style: none; padding: 0; margin: 0 0 1rem; }
+.tags li { display: inline-block; margin-right: 0.5rem; color: #666; }
+.description { font-style: italic; }
+.metadata { display: grid; grid-template-columns: max-content auto; gap: 0.1rem 1rem; }
+.metadata dt { font-weight: bold; }
+.metadata dd { margin: 0; }
+.image { display: block; max-width: 100%; max-height: 24rem; margin: 1rem auto; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 0.2rem 0.5rem; border-bottom: 1px solid #eee; vertical-align: top; }
+td.quantity { text-align: right; white-space: nowrap; }
+.note, .optional { color: #666; font-style: italic; }
+.steps li { margin-bottom: 0.6rem; }
+.ingredient { color: #2e7d32; font-weight: bold; }
+.cookware { color: #b26a00; font-weight: bold; }
+.timer { color: #00838f; font-weight: bold; }
+.inline-quantity { color: #c62828; }
+.step-quantity { color: #666; font-size: 0.9em; }
+@media print {
+  body { margin: 0; max-width: none; font-size: 11pt; }
+  .image { max-height: 8cm; }
+  section, li, tr { break-inside: avoid; }
+}
+"#;
+
+/// Write the recipe as a self-contained HTML page
+///
+/// If an `image` is given it will be embedded in the page, so the output
+/// doesn't depend on any other file.
+pub fn print_html(
+    recipe: &ScaledRecipe,
+    name: &str,
+    scale: f64,
+    image: Option<&Utf8Path>,
+    converter: &Converter,
+    mut writer: impl io::Write,
+) -> Result<()> {
+    let w = &mut writer;
+
+    let title = recipe.metadata.title().unwrap_or(name);
+
+    writeln!(w, "<!DOCTYPE html>\n<html>\n<head>").context("Failed to write head")?;
+    writeln!(w, "<meta charset=\"utf-8\">").context("Failed to write head")?;
+    writeln!(
+        w,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    )
+    .context("Failed to write head")?;
+    writeln!(w, "<title>{}</title>", text(title)).context("Failed to write title")?;
+    writeln!(w, "<style>{STYLE}</style>\n</head>\n<body>\n<article>")
+        .context("Failed to write head")?;
+
+    header(w, recipe, title, scale, converter).context("Failed to write header")?;
+    if let Some(image) = image {
+        embedded_image(w, image, title).context("Failed to write image")?;
+    }
+    ingredients(w, recipe, converter).context("Failed to write ingredients")?;
+    cookware(w, recipe).context("Failed to write cookware")?;
+    sections(w, recipe).context("Failed to write sections")?;
+
+    writeln!(w, "</article>\n</body>\n</html>").context("Failed to write footer")?;
+
+    Ok(())
+}
+
+fn header(
+    w: &mut impl io::Write,
+    recipe: &ScaledRecipe,
+    title: &str,
+    scale: f64,
+    converter: &Converter,
+) -> Result<()> {
+    writeln!(w, "<header>")?;
+    write!(w, "<h1>")?;
+    if let Some(emoji) = recipe.metadata.get("emoji").and_then(|v| v.as_str()) {
+        write!(w, "{} ", text(emoji))?;
+    }
+    write!(w, "{}", text(title))?;
+    if scale != 1.0 {
+        write!(w, " @ {scale}")?;
+    }
+    writeln!(w, "</h1>")?;
+
+    if let Some(tags) = recipe.metadata.tags() {
+        write!(w, "<ul class=\"tags\">")?;
+        for tag in tags {
+            write!(w, "<li>#{}</li>", text(&tag))?;
+        }
+        writeln!(w, "</ul>")?;
+    }
+
+    if let Some(desc) = recipe.metadata.description() {
+        writeln!(w, "<p class=\"description\">{}</p>", text(desc))?;
+    }
+
+    let mut entries: Vec<(Cow<str>, String)> = Vec::new();
+    if let Some(author) = recipe.metadata.author() {
+        entries.push(("author".into(), name_and_url(&author)));
+    }
+    if let Some(source) = recipe.metadata.source() {
+        entries.push(("source".into(), name_and_url(&source)));
+    }
+    if let Some(servings) = recipe
+        .metadata
+        .get("servings")
+        .and_then(|v| v.as_str_like())
+    {
+        entries.push(("servings".into(), text(&servings).into_owned()));
+    }
+    if let Some(time) = recipe.metadata.time(converter) {
+        let time_fmt =
+            |t: u32| humantime::format_duration(Duration::from_secs(t as u64 * 60)).to_string();
+        match time {
+            RecipeTime::Total(t) => entries.push(("time".into(), time_fmt(t))),
+            RecipeTime::Composed {
+                prep_time,
+                cook_time,
+            } => {
+                if let Some(p) = prep_time {
+                    entries.push(("prep time".into(), time_fmt(p)));
+                }
+                if let Some(c) = cook_time {
+                    entries.push(("cook time".into(), time_fmt(c)));
+                }
+                entries.push(("total time".into(), time_fmt(time.total())));
+            }
+        }
+    }
+
+    let others = recipe.metadata.map.iter().filter_map(|(key, value)| {
+        let key = key.as_str_like()?;
+        match key.as_ref() {
+            "name" | "title" | "description" | "tags" | "author" | "source" | "emoji" | "time"
+            | "prep time" | "cook time" | "servings" | "image" => return None,
+            _ => {}
+        }
+        let value = value.as_str_like()?;
+        Some((key, value))
+    });
+    for (key, value) in others {
+        entries.push((key, text(&value).into_owned()));
+    }
+
+    if !entries.is_empty() {
+        writeln!(w, "<dl class=\"metadata\">")?;
+        for (key, value) in entries {
+            writeln!(w, "<dt>{}</dt><dd>{value}</dd>", text(&key))?;
+        }
+        writeln!(w, "</dl>")?;
+    }
+
+    writeln!(w, "</header>")?;
+    Ok(())
+}
+
+fn name_and_url(value: &NameAndUrl) -> String {
+    // only web links, a `javascript:` or `file:` url is shown as text
+    let url = value.url().filter(|url| {
+        let url = url.to_ascii_lowercase();
+        url.starts_with("http://") || url.starts_with("https://")
+    });
+    match (value.name(), url) {
+        (Some(name), Some(url)) => format!("<a href=\"{}\">{}</a>", attr(url), text(name)),
+        (None, Some(url)) => format!("<a href=\"{}\">{}</a>", attr(url), text(url)),
+        (Some(name), None) => text(name).into_owned(),
+        (None, None) => value
+            .url()
+            .map_or("-".to_string(), |url| text(url).into_owned()),
+    }
+}
+
+fn embedded_image(w: &mut impl io::Write, path: &Utf8Path, title: &str) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read image: {path}"))?;
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    let data = base64::engine::general_purpose::STANDARD.encode(data);
+    writeln!(
+        w,
+        "<img class=\"image\" src=\"data:{mime};base64,{data}\" alt=\"{}\">",
+        attr(title)
+    )?;
+    Ok(())
+}
+
+fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Converter) -> Result<()> {
+    let list = recipe.group_ingredients(converter);
+    let list = list
+        .iter()
+        .filter(|entry| entry.ingredient.modifiers().should_be_listed())
+        .collect::<Vec<_>>();
+    if list.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(
+        w,
+        "<section class=\"ingredients\">\n<h2>Ingredients</h2>\n<table>"
+    )?;
+    for entry in list {
+        let igr = entry.ingredient;
+        write!(w, "<tr><td class=\"quantity\">")?;
+        if !entry.quantity.is_empty() {
+            write!(w, "{}", text(&entry.quantity.to_string()))?;
+        }
+        write!(w, "</td><td class=\"name\">{}", text(&igr.display_name()))?;
+        if igr.modifiers().is_optional() {
+            write!(w, " <span class=\"optional\">(optional)</span>")?;
+        }
+        write!(w, "</td><td class=\"note\">")?;
+        if let Some(note) = &igr.note {
+            write!(w, "{}", text(note))?;
+        }
+        writeln!(w, "</td></tr>")?;
+    }
+    writeln!(w, "</table>\n</section>")?;
+    Ok(())
+}
+
+fn cookware(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result<()> {
+    let list = recipe
+        .group_cookware()
+        .into_iter()
+        .filter(|item| item.cookware.modifiers().should_be_listed())
+        .collect::<Vec<_>>();
+    if list.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(w, "<section class=\"cookware\">\n<h2>Cookware</h2>\n<ul>")?;
+    for item in list {
+        let cw = item.cookware;
+        write!(w, "<li>")?;
+        if !item.amount.is_empty() {
+            write!(w, "{} ", text(&item.amount.to_string()))?;
+        }
+        write!(w, "{}", text(cw.display_name()))?;
+        if cw.modifiers().is_optional() {
+            write!(w, " <span class=\"optional\">(optional)</span>")?;
+        }
+        if let Some(note) = &cw.note {
+            write!(w, " <span class=\"note\">({})</span>", text(note))?;
+        }
+        writeln!(w, "</li>")?;
+    }
+    writeln!(w, "</ul>\n</section>")?;
+    Ok(())
+}
+
+fn sections(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result<()> {
+    writeln!(w, "<section class=\"steps\">\n<h2>Steps</h2>")?;
+    for (index, section) in recipe.sections.iter().enumerate() {
+        w_section(w, section, recipe, index + 1)
+            .with_context(|| format!("Failed to write section {}", index + 1))?;
+    }
+    writeln!(w, "</section>")?;
+    Ok(())
+}
+
+fn w_section(
+    w: &mut impl io::Write,
+    section: &Section,
+    recipe: &ScaledRecipe,
+    num: usize,
+) -> Result<()> {
+    if let Some(name) = &section.name {
+        writeln!(w, "<h3>{}</h3>", text(name))?;
+    } else if recipe.sections.len() > 1 {
+        writeln!(w, "<h3>Section {num}</h3>")?;
+    }
+
+    let mut in_list = false;
+    for content in &section.content {
+        match content {
+            cooklang::Content::Step(step) => {
+                if !in_list {
+                    writeln!(w, "<ol start=\"{}\">", step.number)?;
+                    in_list = true;
+                }
+                writeln!(w, "<li>{}</li>", step_html(step, recipe)?)?;
+            }
+            cooklang::Content::Text(t) => {
+                if in_list {
+                    writeln!(w, "</ol>")?;
+                    in_list = false;
+                }
+                writeln!(w, "<p>{}</p>", text(t.trim()))?;
+            }
+        }
+    }
+    if in_list {
+        writeln!(w, "</ol>")?;
+    }
+    Ok(())
+}
+
+fn step_html(step: &Step, recipe: &ScaledRecipe) -> Result<String> {
+    let mut s = String::new();
+    for item in &step.items {
+        match item {
+            Item::Text { value } => s.push_str(&text(value)),
+            &Item::Ingredient { index } => {
+                let igr = &recipe.ingredients[index];
+                write!(
+                    s,
+                    "<span class=\"ingredient\">{}</span>",
+                    text(&igr.display_name())
+                )?;
+                if let Some(q) = &igr.quantity {
+                    write!(
+                        s,
+                        " <span class=\"step-quantity\">({})</span>",
+                        text(&q.to_string())
+                    )?;
+                }
+            }
+            &Item::Cookware { index } => {
+                let cw = &recipe.cookware[index];
+                write!(
+                    s,
+                    "<span class=\"cookware\">{}</span>",
+                    text(cw.display_name())
+                )?;
+            }
+            &Item::Timer { index } => {
+                let t = &recipe.timers[index];
+                let label = match (&t.name, &t.quantity) {
+                    (Some(name), Some(q)) => format!("{name} ({q})"),
+                    (Some(name), None) => name.clone(),
+                    (None, Some(q)) => q.to_string(),
+                    (None, None) => String::new(),
+                };
+                write!(s, "<span class=\"timer\">{}</span>", text(&label))?;
+            }
+            &Item::InlineQuantity { index } => {
+                let q = &recipe.inline_quantities[index];
+                write!(
+                    s,
+                    "<span class=\"inline-quantity\">{}</span>",
+                    text(&q.to_string())
+                )?;
+            }
+        }
+    }
+    Ok(s.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::CooklangParser;
+
+    use super::*;
+    use crate::util::{parse_recipe, Scaling};
+
+    fn html(content: &str, image: Option<&Utf8Path>) -> String {
+        let parser = CooklangParser::canonical();
+        let recipe = parse_recipe(content, &parser, Scaling::Factor(1.0)).unwrap();
+        let mut out = Vec::new();
+        print_html(
+            &recipe,
+            "Fallback",
+            1.0,
+            image,
+            parser.converter(),
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn escapes() {
+        let out = html(
+            "---\ntitle: Fish & <Chips>\n---\nFry the @\"fish\"{1} in a #pan<b>{}.",
+            None,
+        );
+        assert!(out.contains("<title>Fish &amp; &lt;Chips&gt;</title>"));
+        assert!(out.contains("<h1>Fish &amp; &lt;Chips&gt;</h1>"));
+        assert!(!out.contains("<Chips>"));
+        assert!(out.contains("<span class=\"cookware\">pan&lt;b&gt;</span>"));
+        assert!(!out.contains("<b>"));
+    }
+
+    #[test]
+    fn links() {
+        let out = html(
+            "---\nauthor: Jane <https://example.com/?a=1&b=\"2\">\nsource: Evil <javascript:alert(1)>\n---\nMix.",
+            None,
+        );
+        assert!(out.contains(
+            "<dt>author</dt><dd><a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">Jane</a></dd>"
+        ));
+        assert!(out.contains("<dt>source</dt><dd>Evil</dd>"));
+        assert!(!out.contains("javascript:"));
+
+        let out = html("---\nsource: file:///etc/passwd\n---\nMix.", None);
+        assert!(out.contains("<dt>source</dt><dd>file:///etc/passwd</dd>"));
+        assert!(!out.contains("<a "));
+    }
+
+    #[test]
+    fn embedded_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("Bread.png");
+        std::fs::write(&path, b"png").unwrap();
+
+        let out = html("---\ntitle: Bread\n---\nBake.", Some(&path));
+        assert!(
+            out.contains("<img class=\"image\" src=\"data:image/png;base64,cG5n\" alt=\"Bread\">")
+        );
+    }
+}
@@ -184,7 +184,7 @@ fn tag_color(tag: &str) -> yansi::Color {
         .enumerate()
         .map(|(i, c)| c as usize * i)
         .reduce(usize::wrapping_add)
-        .map(|h| (h % 7))
+        .map(|h| h % 7)
         .unwrap_or_default();
     match hash {
         0 => yansi::Color::Red,
@@ -309,8 +309,8 @@ fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Conver
 
         let mut row = Row::new().with_cell(igr.display_name());
 
-        if igr.reference.is_some() {
-            let path = igr.reference.as_ref().unwrap().components.join("/");
+        if let Some(reference) = &igr.reference {
+            let path = reference.components.join("/");
             row.add_ansi_cell(
                 format!("(recipe: {}/{})", path, igr.name).paint(styles().reference_marker),
             );
@@ -195,20 +195,6 @@ where
     Ok(v)
 }
 
-/// Writes a recipe in Markdown format
-///
-/// This is an alias for [`print_md_with_options`] where the options are the
-/// default value.
-pub fn print_md(
-    recipe: &ScaledRecipe,
-    name: &str,
-    scale: f64,
-    converter: &Converter,
-    writer: impl io::Write,
-) -> Result<()> {
-    print_md_with_options(recipe, name, scale, &Options::default(), converter, writer)
-}
-
 /// Writes a recipe in Markdown format
 ///
 /// The metadata of the recipe will be in a YAML front-matter. Some special keys
@@ -332,8 +318,8 @@ fn ingredients(
             }
         }
 
-        if ingredient.reference.is_some() {
-            let path = ingredient.reference.as_ref().unwrap().components.join("/");
+        if let Some(reference) = &ingredient.reference {
+            let path = reference.components.join("/");
             write!(
                 w,
                 "[{}]({}/{})",
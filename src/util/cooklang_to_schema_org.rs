@@ -0,0 +1,310 @@
+//! Format a recipe as a schema.org `Recipe` JSON-LD document
+//!
+//! See <https://schema.org/Recipe>.
+
+use std::fmt::Write;
+
+use cooklang::{
+    convert::Converter,
+    metadata::{CooklangValueExt, NameAndUrl, RecipeTime, StdKey},
+    model::{Item, Step},
+    ScaledRecipe,
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaRecipe {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<Person>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_based_on: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    image: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keywords: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recipe_category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recipe_cuisine: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recipe_yield: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prep_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cook_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_time: Option<String>,
+    recipe_ingredient: Vec<String>,
+    recipe_instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Serialize)]
+struct Person {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "@type")]
+enum Instruction {
+    #[serde(rename = "HowToStep")]
+    Step { text: String },
+    #[serde(rename = "HowToSection", rename_all = "camelCase")]
+    Section {
+        name: String,
+        item_list_element: Vec<Instruction>,
+    },
+}
+
+/// Build the schema.org representation of a recipe
+///
+/// `name` is used when the recipe has no `title` in its metadata.
+pub fn to_schema_org(
+    recipe: &ScaledRecipe,
+    name: &str,
+    scale: f64,
+    converter: &Converter,
+) -> SchemaRecipe {
+    let meta = &recipe.metadata;
+
+    let (prep_time, cook_time, total_time) = match meta.time(converter) {
+        Some(RecipeTime::Total(t)) => (None, None, Some(t)),
+        Some(
+            time @ RecipeTime::Composed {
+                prep_time,
+                cook_time,
+            },
+        ) => (prep_time, cook_time, Some(time.total())),
+        None => (None, None, None),
+    };
+
+    let recipe_yield = meta
+        .servings()
+        .and_then(|s| s.first().copied())
+        .map(|servings| {
+            let servings = (servings as f64 * scale * 100.0).round() / 100.0;
+            format!("{servings} servings")
+        });
+
+    let image = match meta.get(StdKey::Images) {
+        Some(serde_yaml::Value::Sequence(images)) => images
+            .iter()
+            .filter_map(|i| i.as_str_like())
+            .map(Into::into)
+            .collect(),
+        Some(image) => image.as_str_like().map(Into::into).into_iter().collect(),
+        None => vec![],
+    };
+
+    SchemaRecipe {
+        context: "https://schema.org",
+        kind: "Recipe",
+        name: meta.title().unwrap_or(name).to_string(),
+        description: meta.description().map(String::from),
+        author: meta.author().map(|author| person(&author)),
+        is_based_on: meta
+            .source()
+            .and_then(|source| source.url().or(source.name()).map(String::from)),
+        image,
+        keywords: meta.tags().map(|tags| tags.join(", ")),
+        recipe_category: text_key(recipe, StdKey::Course),
+        recipe_cuisine: text_key(recipe, StdKey::Cuisine),
+        in_language: meta.locale().map(|(lang, country)| match country {
+            Some(country) => format!("{lang}-{country}"),
+            None => lang.to_string(),
+        }),
+        recipe_yield,
+        prep_time: prep_time.map(iso_duration),
+        cook_time: cook_time.map(iso_duration),
+        total_time: total_time.map(iso_duration),
+        recipe_ingredient: ingredients(recipe, converter),
+        recipe_instructions: instructions(recipe),
+    }
+}
+
+fn text_key(recipe: &ScaledRecipe, key: StdKey) -> Option<String> {
+    recipe
+        .metadata
+        .get(key)
+        .and_then(|v| v.as_str_like())
+        .map(Into::into)
+}
+
+fn person(value: &NameAndUrl) -> Person {
+    Person {
+        kind: "Person",
+        name: value.name().map(String::from),
+        url: value.url().map(String::from),
+    }
+}
+
+/// ISO 8601 duration from minutes, like `PT1H30M`
+fn iso_duration(minutes: u32) -> String {
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    let mut s = String::from("PT");
+    if hours > 0 {
+        write!(s, "{hours}H").unwrap();
+    }
+    if minutes > 0 || hours == 0 {
+        write!(s, "{minutes}M").unwrap();
+    }
+    s
+}
+
+fn ingredients(recipe: &ScaledRecipe, converter: &Converter) -> Vec<String> {
+    recipe
+        .group_ingredients(converter)
+        .into_iter()
+        .filter(|entry| entry.ingredient.modifiers().should_be_listed())
+        .map(|entry| {
+            let igr = entry.ingredient;
+            let mut s = String::new();
+            if !entry.quantity.is_empty() {
+                write!(s, "{} ", entry.quantity).unwrap();
+            }
+            s.push_str(&igr.display_name());
+            if let Some(note) = &igr.note {
+                write!(s, ", {note}").unwrap();
+            }
+            if igr.modifiers().is_optional() {
+                s.push_str(" (optional)");
+            }
+            s
+        })
+        .collect()
+}
+
+fn instructions(recipe: &ScaledRecipe) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    for section in &recipe.sections {
+        let steps = section
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                cooklang::Content::Step(step) => Some(Instruction::Step {
+                    text: step_text(step, recipe),
+                }),
+                cooklang::Content::Text(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        match &section.name {
+            Some(name) => instructions.push(Instruction::Section {
+                name: name.clone(),
+                item_list_element: steps,
+            }),
+            None => instructions.extend(steps),
+        }
+    }
+    instructions
+}
+
+fn step_text(step: &Step, recipe: &ScaledRecipe) -> String {
+    let mut s = String::new();
+    for item in &step.items {
+        match item {
+            Item::Text { value } => s.push_str(value),
+            &Item::Ingredient { index } => {
+                s.push_str(&recipe.ingredients[index].display_name());
+            }
+            &Item::Cookware { index } => {
+                s.push_str(recipe.cookware[index].display_name());
+            }
+            &Item::Timer { index } => {
+                let t = &recipe.timers[index];
+                match (&t.name, &t.quantity) {
+                    (Some(name), Some(q)) => write!(s, "{name} ({q})").unwrap(),
+                    (Some(name), None) => s.push_str(name),
+                    (None, Some(q)) => write!(s, "{q}").unwrap(),
+                    (None, None) => {}
+                }
+            }
+            &Item::InlineQuantity { index } => {
+                write!(s, "{}", recipe.inline_quantities[index]).unwrap();
+            }
+        }
+    }
+    s.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::{Converter, CooklangParser, Extensions};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::util::{parse_recipe, Scaling};
+
+    fn schema(content: &str, scale: f64) -> Value {
+        let parser = CooklangParser::new(Extensions::all(), Converter::bundled());
+        let recipe = parse_recipe(content, &parser, Scaling::Factor(scale)).unwrap();
+        serde_json::to_value(to_schema_org(
+            &recipe,
+            "Fallback",
+            scale,
+            parser.converter(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn recipe_yield() {
+        let content = "---\nservings: 4\n---\nMix @flour{500%g}.";
+        assert_eq!(schema(content, 1.0)["recipeYield"], "4 servings");
+        assert_eq!(schema(content, 1.5)["recipeYield"], "6 servings");
+        assert_eq!(schema(content, 0.3)["recipeYield"], "1.2 servings");
+        assert_eq!(
+            schema(content, 1.5)["recipeIngredient"],
+            json!(["750 g flour"])
+        );
+        assert_eq!(schema("Mix.", 2.0).get("recipeYield"), None);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(iso_duration(0), "PT0M");
+        assert_eq!(iso_duration(45), "PT45M");
+        assert_eq!(iso_duration(60), "PT1H");
+        assert_eq!(iso_duration(90), "PT1H30M");
+
+        let recipe = schema("---\nprep time: 15 min\ncook time: 1 hour\n---\nMix.", 1.0);
+        assert_eq!(recipe["prepTime"], "PT15M");
+        assert_eq!(recipe["cookTime"], "PT1H");
+        assert_eq!(recipe["totalTime"], "PT1H15M");
+
+        let recipe = schema("---\ntime: 2 hours\n---\nMix.", 1.0);
+        assert_eq!(recipe.get("prepTime"), None);
+        assert_eq!(recipe["totalTime"], "PT2H");
+    }
+
+    #[test]
+    fn source() {
+        let based_on = |source: &str| {
+            schema(&format!("---\nsource: {source}\n---\nMix."), 1.0)["isBasedOn"].clone()
+        };
+        assert_eq!(
+            based_on("Grandma <https://example.com/bread>"),
+            "https://example.com/bread"
+        );
+        assert_eq!(
+            based_on("https://example.com/bread"),
+            "https://example.com/bread"
+        );
+        assert_eq!(based_on("Grandma's notebook"), "Grandma's notebook");
+        assert_eq!(schema("Mix.", 1.0).get("isBasedOn"), None);
+    }
+}
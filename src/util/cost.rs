@@ -0,0 +1,198 @@
+//! Cost of shopping list items from the prices in the db
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use cooklang::{
+    ingredient_list::IngredientList,
+    quantity::{Number, Value},
+    Converter, ScaledQuantity,
+};
+
+use super::{
+    db::{Db, Shopping},
+    number,
+    packages::Packages,
+    units::with_units,
+};
+
+/// Cost of every item, by ingredient name
+///
+/// `None` when the ingredient has no price or its quantity can't be
+/// converted to the unit of the price.
+#[derive(Debug, Default)]
+pub struct Costs(BTreeMap<String, Option<f64>>);
+
+impl Costs {
+    /// Estimates the cost of every item of the list
+    ///
+    /// Items rounded to packages cost what the packages do.
+    pub fn estimate(
+        list: &IngredientList,
+        packages: Option<&Packages>,
+        db: &Db,
+        converter: &Converter,
+    ) -> Result<Self> {
+        let converter = with_units(converter);
+        let mut costs = Self::default();
+        for (name, quantities) in list.iter() {
+            let to_buy = match packages.and_then(|p| p.get(name)) {
+                Some(rounded) => rounded
+                    .packages
+                    .iter()
+                    .map(|p| scaled(&p.size, p.count as f64))
+                    .chain(rounded.rest.iter().cloned())
+                    .collect(),
+                None => quantities.iter().cloned().collect::<Vec<_>>(),
+            };
+
+            let cost = match db.shopping(name)? {
+                // without a quantity there's nothing to price
+                Some(_) if to_buy.is_empty() => None,
+                Some(shopping) => to_buy
+                    .iter()
+                    .map(|q| cost(q, &shopping, &converter))
+                    .sum::<Option<f64>>(),
+                None => None,
+            };
+            costs.0.insert(name.clone(), cost);
+        }
+        Ok(costs)
+    }
+
+    /// Cost of an item, `None` if it's unknown or the item is not in the list
+    pub fn get(&self, ingredient: &str) -> Option<f64> {
+        self.0.get(ingredient).copied().flatten()
+    }
+
+    /// Sum of the known costs
+    pub fn total(&self) -> f64 {
+        self.0
+            .values()
+            .flatten()
+            .fold(0.0, |total, cost| total + cost)
+    }
+
+    /// Items without a known cost
+    pub fn missing(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .filter(|(_, cost)| cost.is_none())
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+fn cost(quantity: &ScaledQuantity, shopping: &Shopping, converter: &Converter) -> Option<f64> {
+    let price = shopping.price_per_unit?;
+    let Some(unit) = &shopping.unit else {
+        // a price for things counted, like eggs, not for `200 g` of them
+        if quantity.unit().is_some() {
+            return None;
+        }
+        return Some(number(quantity)? * price);
+    };
+
+    let price_unit = ScaledQuantity::new(Value::from(1.0), Some(unit.clone()));
+    let mut quantity = quantity.clone();
+    if let Some(unit) = price_unit.compatible_unit(&quantity, converter).ok()? {
+        quantity.convert(&unit, converter).ok()?;
+    }
+    Some(number(&quantity)? * price)
+}
+
+fn scaled(quantity: &ScaledQuantity, factor: f64) -> ScaledQuantity {
+    let value = match quantity.value() {
+        Value::Number(n) => Value::Number(Number::Regular(n.value() * factor)),
+        other => other.clone(),
+    };
+    ScaledQuantity::new(value, quantity.unit().map(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::quantity::GroupedQuantity;
+
+    use super::*;
+    use crate::util::{db::DB_DIR, parse_quantity};
+
+    fn shopping(price: f64, unit: Option<&str>) -> Shopping {
+        Shopping {
+            price_per_unit: Some(price),
+            unit: unit.map(String::from),
+            packages: Vec::new(),
+        }
+    }
+
+    fn cost_of(quantity: &str, shopping: &Shopping) -> Option<f64> {
+        let converter = Converter::bundled();
+        cost(&parse_quantity(quantity).unwrap(), shopping, &converter)
+    }
+
+    #[test]
+    fn converts_to_the_price_unit() {
+        let per_kg = shopping(2.0, Some("kg"));
+        assert_eq!(cost_of("1.5 kg", &per_kg), Some(3.0));
+        assert_eq!(cost_of("500 g", &per_kg), Some(1.0));
+        assert_eq!(cost_of("2 cups", &per_kg), None);
+        assert_eq!(cost_of("3", &per_kg), None);
+
+        let per_ball = shopping(0.5, Some("balls"));
+        assert_eq!(cost_of("4 balls", &per_ball), Some(2.0));
+        assert_eq!(cost_of("4 slices", &per_ball), None);
+    }
+
+    #[test]
+    fn unitless_price() {
+        let per_egg = shopping(0.25, None);
+        assert_eq!(cost_of("6", &per_egg), Some(1.5));
+        assert_eq!(cost_of("200 g", &per_egg), None);
+        assert_eq!(cost_of("1 dozen", &per_egg), None);
+
+        let no_price = Shopping::default();
+        assert_eq!(cost_of("6", &no_price), None);
+    }
+
+    #[test]
+    fn estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = camino::Utf8Path::from_path(dir.path()).unwrap();
+        for (name, shopping) in [
+            ("flour", "price_per_unit: 0.002\nunit: g\npackages: 1 kg"),
+            ("eggs", "price_per_unit: 0.25"),
+            ("salt", "packages: 250 g"),
+        ] {
+            let dir = base_path.join(DB_DIR).join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("shopping.yml"), shopping).unwrap();
+        }
+
+        let converter = Converter::bundled();
+        let mut list = IngredientList::new();
+        for (name, quantity) in [
+            ("flour", "1.2 kg"),
+            ("eggs", "3"),
+            ("eggs", "100 g"),
+            ("salt", "5 g"),
+            ("basil", "1 bunch"),
+        ] {
+            let mut grouped = GroupedQuantity::empty();
+            grouped.add(&parse_quantity(quantity).unwrap(), &converter);
+            list.add_ingredient(name.to_string(), &grouped, &converter);
+        }
+
+        let db = Db::new(base_path);
+        let costs = Costs::estimate(&list, None, &db, &converter).unwrap();
+        assert!((costs.get("flour").unwrap() - 2.4).abs() < 1e-9);
+        // one of the quantities can't be priced
+        assert_eq!(costs.get("eggs"), None);
+        assert_eq!(
+            costs.missing().collect::<Vec<_>>(),
+            ["basil", "eggs", "salt"]
+        );
+
+        let packages = Packages::round(&list, &db, &converter).unwrap();
+        let costs = Costs::estimate(&list, Some(&packages), &db, &converter).unwrap();
+        assert!((costs.get("flour").unwrap() - 4.0).abs() < 1e-9);
+        assert!((costs.total() - 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,107 @@
+//! Ingredient database
+//!
+//! A `db` dir in the base path with a dir for every ingredient. What's needed
+//! to buy it is in `shopping.yml`:
+//!
+//! ```yaml
+//! price_per_unit: 0.0015
+//! # unit of the price, none for things counted like eggs
+//! unit: g
+//! # sizes it's sold in, a number for things counted
+//! packages: [500 g, 1 kg]
+//! ```
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::ScaledQuantity;
+use serde::Deserialize;
+
+use super::parse_quantity;
+
+pub const DB_DIR: &str = "db";
+
+pub struct Db {
+    dir: Utf8PathBuf,
+}
+
+/// Contents of `shopping.yml`
+#[derive(Debug, Clone, Default)]
+pub struct Shopping {
+    pub price_per_unit: Option<f64>,
+    /// Unit of [`Self::price_per_unit`]
+    ///
+    /// Without it, the price is for things counted, like eggs, and only
+    /// quantities without a unit can be priced.
+    pub unit: Option<String>,
+    pub packages: Vec<ScaledQuantity>,
+}
+
+#[derive(Deserialize)]
+struct RawShopping {
+    price_per_unit: Option<f64>,
+    unit: Option<String>,
+    #[serde(default)]
+    packages: Option<OneOrMany>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(RawPackage),
+    Many(Vec<RawPackage>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPackage {
+    Number(f64),
+    Text(String),
+}
+
+impl Db {
+    pub fn new(base_path: &Utf8Path) -> Self {
+        Self {
+            dir: base_path.join(DB_DIR),
+        }
+    }
+
+    /// Shopping info of an ingredient, if it has any
+    ///
+    /// The dir is named like the ingredient, or like it in lowercase.
+    pub fn shopping(&self, ingredient: &str) -> Result<Option<Shopping>> {
+        let path = [ingredient.to_string(), ingredient.to_lowercase()]
+            .into_iter()
+            .map(|name| self.dir.join(name).join("shopping.yml"))
+            .find(|path| path.is_file());
+        path.map(|path| load(&path)).transpose()
+    }
+}
+
+fn load(path: &Utf8Path) -> Result<Shopping> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+    let raw: RawShopping =
+        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {path}"))?;
+
+    let packages = match raw.packages {
+        None => Vec::new(),
+        Some(OneOrMany::One(p)) => vec![p],
+        Some(OneOrMany::Many(p)) => p,
+    };
+    let packages = packages
+        .into_iter()
+        .map(|p| {
+            let text = match p {
+                RawPackage::Number(n) => n.to_string(),
+                RawPackage::Text(s) => s,
+            };
+            parse_quantity(&text).with_context(|| format!("Invalid package '{text}' in {path}"))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Shopping {
+        price_per_unit: raw.price_per_unit,
+        unit: raw.unit,
+        packages,
+    })
+}
@@ -0,0 +1,35 @@
+use cooklang::error::SourceDiag;
+use serde::Serialize;
+
+/// Serializable parser diagnostic with a line and column instead of a span
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    severity: &'static str,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    hints: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(diag: &SourceDiag, content: &str) -> Self {
+        let (line, column) = diag
+            .labels
+            .first()
+            .map(|(span, _)| {
+                let before = &content[..span.start()];
+                let line = before.matches('\n').count() + 1;
+                let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+                (Some(line), Some(column))
+            })
+            .unwrap_or_default();
+
+        Self {
+            severity: if diag.is_error() { "error" } else { "warning" },
+            message: diag.message.to_string(),
+            line,
+            column,
+            hints: diag.hints.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+}
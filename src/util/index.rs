@@ -0,0 +1,270 @@
+//! On-disk index of the recipes in a directory
+//!
+//! Parsing every recipe on each search gets slow with big collections, so the
+//! extracted [`RecipeInfo`] is stored in `.cook-cache/index.json` inside the
+//! base path. Entries are keyed by the file modification time and size, and
+//! by a hash of the content when those change, so only new or edited recipes
+//! are parsed again.
+
+use std::{collections::BTreeMap, io::Write, time::UNIX_EPOCH};
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::CooklangParser;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use super::{find_recipe_files, search::RecipeInfo};
+
+pub const CACHE_DIR: &str = ".cook-cache";
+const INDEX_FILE: &str = "index.json";
+
+/// Bump when [`RecipeInfo`] changes
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    /// Parser configuration the entries were extracted with
+    parser: String,
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Nanoseconds since the unix epoch
+    modified: u128,
+    size: u64,
+    hash: String,
+    info: RecipeInfo,
+}
+
+/// Information of every recipe in `base_path`, sorted by path
+///
+/// The index is refreshed and saved if anything changed. Failing to save it
+/// is not an error, as it's only a cache.
+pub fn recipes(base_path: &Utf8Path, parser: &CooklangParser) -> Result<Vec<RecipeInfo>> {
+    let index_path = base_path.join(CACHE_DIR).join(INDEX_FILE);
+    let fingerprint = parser_fingerprint(parser);
+
+    let mut index = load(&index_path)
+        .filter(|index| index.version == VERSION && index.parser == fingerprint)
+        .unwrap_or_else(|| Index {
+            version: VERSION,
+            parser: fingerprint,
+            entries: BTreeMap::new(),
+        });
+
+    let mut changed = false;
+    let mut entries = BTreeMap::new();
+
+    for path in find_recipe_files(base_path)? {
+        let relative = path.strip_prefix(base_path).unwrap_or(&path);
+        let key = relative.as_str().replace('\\', "/");
+
+        let fs_meta = path
+            .metadata()
+            .with_context(|| format!("Failed to read recipe file: {path}"))?;
+        let modified = fs_meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let size = fs_meta.len();
+
+        let old = match index.entries.remove(&key) {
+            Some(entry) if entry.modified == modified && entry.size == size => {
+                entries.insert(key, entry);
+                continue;
+            }
+            old => old,
+        };
+
+        changed = true;
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read recipe file: {path}"))?;
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+
+        let info = match old {
+            Some(entry) if entry.hash == hash => entry.info,
+            _ => {
+                debug!("Indexing {key}");
+                RecipeInfo::new(relative, &content, parser)
+            }
+        };
+
+        entries.insert(
+            key,
+            Entry {
+                modified,
+                size,
+                hash,
+                info,
+            },
+        );
+    }
+
+    // anything left was removed
+    changed |= !index.entries.is_empty();
+    index.entries = entries;
+
+    if changed {
+        if let Err(e) = save(base_path, &index) {
+            warn!("Failed to save recipe index: {e:#}");
+        }
+    }
+
+    Ok(index.entries.into_values().map(|e| e.info).collect())
+}
+
+fn parser_fingerprint(parser: &CooklangParser) -> String {
+    let converter = parser.converter();
+    format!(
+        "{:?};{:?};{}",
+        parser.extensions(),
+        converter.default_system(),
+        converter.unit_count()
+    )
+}
+
+fn load(path: &Utf8Path) -> Option<Index> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(index) => Some(index),
+        Err(e) => {
+            warn!("Ignoring invalid recipe index {path}: {e}");
+            None
+        }
+    }
+}
+
+fn save(base_path: &Utf8Path, index: &Index) -> Result<()> {
+    let dir = base_path.join(CACHE_DIR);
+    if !dir.exists() {
+        std::fs::create_dir(&dir).with_context(|| format!("Failed to create {dir}"))?;
+        // the cache should never end up in a recipes repository
+        std::fs::write(dir.join(".gitignore"), "*\n")?;
+    }
+
+    // write and rename so concurrent readers never see half an index
+    let mut file = tempfile::NamedTempFile::new_in(&dir)?;
+    serde_json::to_writer(&mut file, index)?;
+    file.flush()?;
+    file.persist(dir.join(INDEX_FILE))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, time::Duration};
+
+    use camino::Utf8PathBuf;
+    use cooklang::{Converter, Extensions};
+
+    use super::*;
+
+    struct Collection {
+        _dir: tempfile::TempDir,
+        base_path: Utf8PathBuf,
+    }
+
+    impl Collection {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+            std::fs::write(base_path.join("Bread.cook"), "Mix @flour{500%g}.").unwrap();
+            Self {
+                _dir: dir,
+                base_path,
+            }
+        }
+
+        fn recipe(&self) -> Utf8PathBuf {
+            self.base_path.join("Bread.cook")
+        }
+
+        fn index(&self) -> Utf8PathBuf {
+            self.base_path.join(CACHE_DIR).join(INDEX_FILE)
+        }
+
+        fn names(&self, parser: &CooklangParser) -> Vec<String> {
+            recipes(&self.base_path, parser)
+                .unwrap()
+                .into_iter()
+                .map(|info| info.name)
+                .collect()
+        }
+
+        /// Rename the cached entries, so a name shows if the cache was used
+        fn mark_cached(&self) {
+            let mut index = load(&self.index()).unwrap();
+            for entry in index.entries.values_mut() {
+                entry.info.name = "cached".to_string();
+            }
+            save(&self.base_path, &index).unwrap();
+        }
+    }
+
+    fn set_modified(path: &Utf8Path, modified: std::time::SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn unchanged_files_hit() {
+        let parser = CooklangParser::canonical();
+        let collection = Collection::new();
+        assert_eq!(collection.names(&parser), ["Bread"]);
+        assert!(collection.index().exists());
+
+        collection.mark_cached();
+        assert_eq!(collection.names(&parser), ["cached"]);
+    }
+
+    #[test]
+    fn changed_files_miss() {
+        let parser = CooklangParser::canonical();
+        let collection = Collection::new();
+        collection.names(&parser);
+        collection.mark_cached();
+
+        // only touched, the content hash still matches
+        let modified = collection.recipe().metadata().unwrap().modified().unwrap();
+        set_modified(&collection.recipe(), modified + Duration::from_secs(60));
+        assert_eq!(collection.names(&parser), ["cached"]);
+
+        // same modification time, different content
+        let modified = collection.recipe().metadata().unwrap().modified().unwrap();
+        std::fs::write(collection.recipe(), "Mix @flour{1%kg}.").unwrap();
+        set_modified(&collection.recipe(), modified);
+        assert_eq!(collection.names(&parser), ["Bread"]);
+    }
+
+    #[test]
+    fn parser_change_misses() {
+        let collection = Collection::new();
+        collection.names(&CooklangParser::canonical());
+        collection.mark_cached();
+
+        let parser = CooklangParser::new(Extensions::empty(), Converter::bundled());
+        assert_eq!(collection.names(&parser), ["Bread"]);
+    }
+
+    #[test]
+    fn corrupt_index() {
+        let parser = CooklangParser::canonical();
+        let collection = Collection::new();
+        collection.names(&parser);
+
+        std::fs::write(collection.index(), "{\"version\": 1, \"entr").unwrap();
+        assert_eq!(collection.names(&parser), ["Bread"]);
+        // and it's written again
+        assert!(load(&collection.index()).is_some());
+    }
+}
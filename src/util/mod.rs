@@ -29,18 +29,89 @@
 // SOFTWARE.
 
 pub mod cooklang_to_cooklang;
+pub mod cooklang_to_html;
 pub mod cooklang_to_human;
 pub mod cooklang_to_md;
+pub mod cooklang_to_schema_org;
+pub mod cost;
+pub mod db;
+pub mod diagnostic;
+pub mod index;
+pub mod packages;
+pub mod pantry;
+pub mod plan;
+pub mod search;
+pub mod sources;
+pub mod units;
 
-use anyhow::{Context as _, Result};
+use anyhow::{bail, Context as _, Result};
 use camino::{Utf8Path, Utf8PathBuf};
-use clap::CommandFactory;
-use cooklang::{ingredient_list::IngredientList, quantity::Value, Converter};
+use clap::ValueEnum;
+use cooklang::{
+    aisle::AisleConf,
+    ingredient_list::IngredientList,
+    metadata::CooklangValueExt,
+    quantity::{Number, Value},
+    CooklangParser, Metadata, ScaledQuantity, ScaledRecipe,
+};
 use cooklang_find::RecipeEntry;
 use std::collections::BTreeMap;
 
 pub const RECIPE_SCALING_DELIMITER: char = ':';
 
+/// How much to scale a recipe
+///
+/// Written as a factor, like `2`, or as servings with a `p` suffix, like `4p`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    Factor(f64),
+    /// Target number of servings
+    Servings(u32),
+}
+
+impl std::str::FromStr for Scaling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(servings) = s.strip_suffix('p') {
+            let servings = servings
+                .parse::<u32>()
+                .with_context(|| format!("Invalid number of servings: '{s}'"))?;
+            return Ok(Self::Servings(servings));
+        }
+        let factor = s.parse::<f64>().with_context(|| {
+            format!("Invalid scale '{s}'. Use a factor like `2` or servings like `4p`")
+        })?;
+        Ok(Self::Factor(factor))
+    }
+}
+
+impl Scaling {
+    /// Scaling factor for a recipe with the given metadata
+    ///
+    /// Fails when scaling to servings and the recipe has none defined.
+    pub fn factor(self, metadata: &Metadata) -> Result<f64> {
+        match self {
+            Self::Factor(factor) => Ok(factor),
+            Self::Servings(0) => bail!("The number of servings must be greater than 0"),
+            Self::Servings(target) => {
+                let base = metadata
+                    .servings()
+                    .and_then(|s| s.first().copied())
+                    .filter(|&s| s > 0)
+                    .with_context(|| {
+                        format!(
+                            "Can't scale to {target} servings: \
+                             the recipe has no servings in its metadata"
+                        )
+                    })?;
+                Ok(target as f64 / base as f64)
+            }
+        }
+    }
+}
+
 pub fn write_to_output<F>(output: Option<&Utf8Path>, f: F) -> Result<()>
 where
     F: FnOnce(Box<dyn std::io::Write>) -> Result<()>,
@@ -56,6 +127,26 @@ where
     Ok(())
 }
 
+/// Parses an aisle file, printing a detailed report of the error if it fails
+pub fn parse_aisle<'a>(path: &Utf8Path, content: &'a str) -> Result<AisleConf<'a>> {
+    match cooklang::aisle::parse(content) {
+        Ok(conf) => Ok(conf),
+        Err(e) => {
+            let stderr = std::io::stderr();
+            let color = anstream::AutoStream::choice(&stderr) != anstream::ColorChoice::Never;
+            cooklang::error::write_rich_error(&e, path.as_str(), content, color, stderr)?;
+            bail!("Error parsing aisle file")
+        }
+    }
+}
+
+/// Parses an output format given in the config file
+pub fn config_format<T: ValueEnum>(format: Option<&str>) -> Result<Option<T>> {
+    format
+        .map(|f| T::from_str(f, true).map_err(|e| anyhow::anyhow!("Invalid format in config: {e}")))
+        .transpose()
+}
+
 pub fn split_recipe_name_and_scaling_factor(query: &str) -> Option<(&str, &str)> {
     query.trim().rsplit_once(RECIPE_SCALING_DELIMITER)
 }
@@ -98,8 +189,9 @@ pub fn extract_ingredients(
     list: &mut IngredientList,
     seen: &mut BTreeMap<String, usize>,
     base_path: &Utf8PathBuf,
-    converter: &Converter,
+    parser: &CooklangParser,
     ignore_references: bool,
+    mut sources: Option<&mut sources::Sources>,
 ) -> Result<()> {
     if seen.contains_key(entry) {
         return Err(anyhow::anyhow!(
@@ -112,56 +204,64 @@ pub fn extract_ingredients(
     seen.insert(entry.to_string(), seen.len());
 
     // split into name and servings
-    let (name, scaling_factor) = split_recipe_name_and_scaling_factor(entry)
-        .map(|(name, scaling_factor)| {
-            let target = scaling_factor.parse::<f64>().unwrap_or_else(|err| {
-                let mut cmd = crate::CliArgs::command();
-                cmd.error(
-                    clap::error::ErrorKind::InvalidValue,
-                    format!("Invalid scaling target for '{name}': {err}"),
-                )
-                .exit()
-            });
+    let (name, scaling) = match split_recipe_name_and_scaling_factor(entry) {
+        Some((name, scaling)) => {
+            let target = scaling
+                .parse::<Scaling>()
+                .with_context(|| format!("Invalid scaling target for '{name}'"))?;
             (name, target)
-        })
-        .unwrap_or((entry, 1.0));
+        }
+        None => (entry, Scaling::Factor(1.0)),
+    };
 
     let recipe_entry = get_recipe(base_path, name)?;
-    let recipe = recipe_entry.recipe(scaling_factor);
-    let ref_indices = list.add_recipe(&recipe, converter, ignore_references);
+    let recipe = parse_recipe_entry(&recipe_entry, parser, scaling)?;
+    let scaling_factor = scaling.factor(&recipe.metadata)?;
+    let ref_indices = list.add_recipe(&recipe, parser.converter(), ignore_references);
+    if let Some(sources) = sources.as_deref_mut() {
+        let path = recipe_entry.path().as_ref().context("Recipe has no path")?;
+        let path = path.strip_prefix(base_path).unwrap_or(path);
+        sources.add_recipe(
+            path.as_str(),
+            &recipe,
+            parser.converter(),
+            ignore_references,
+        );
+    }
 
     if !ignore_references {
         for ref_index in ref_indices {
             let ingredient = &recipe.ingredients[ref_index];
             let reference = ingredient.reference.as_ref().unwrap();
 
+            // references in the same directory have no components
+            let reference_path = reference.path("/").trim_start_matches('/').to_string();
+
             let suffix = match ingredient.quantity.as_ref() {
-                Some(quantity) => {
-                    if quantity.unit().is_some() {
-                        return Err(anyhow::anyhow!(
-                            "Unit not supported for referenced ingredients: {}({}). See https://github.com/cooklang/cookcli/issues/137",
-                            ingredient.name,
-                            quantity
-                        ));
-                    } else {
-                        match quantity.value() {
-                            Value::Number(value) => value.to_string(),
-                            _ => String::from(""),
-                        }
-                    }
+                Some(quantity) if quantity.unit().is_some() => {
+                    reference_scale(quantity, &reference_path, base_path, parser)
+                        .with_context(|| {
+                            format!("Failed to scale {}({quantity})", ingredient.name)
+                        })?
+                        .to_string()
                 }
+                Some(quantity) => match quantity.value() {
+                    Value::Number(value) => value.to_string(),
+                    _ => String::from(""),
+                },
                 None => scaling_factor.to_string(),
             };
 
-            let path = reference.path("/") + ":" + &suffix;
+            let path = reference_path + ":" + &suffix;
 
             extract_ingredients(
                 path.as_str(),
                 list,
                 seen,
                 base_path,
-                converter,
+                parser,
                 ignore_references,
+                sources.as_deref_mut(),
             )?;
         }
     }
@@ -171,9 +271,334 @@ pub fn extract_ingredients(
     Ok(())
 }
 
+/// Scaling factor for a referenced recipe from a quantity with units
+///
+/// The quantity is compared with the `yield` in the metadata of the
+/// referenced recipe, like `@./Pizza Dough{6%balls}` with `yield: 6 balls`.
+/// Units are converted if needed. A quantity in servings is compared with
+/// `servings`.
+fn reference_scale(
+    quantity: &ScaledQuantity,
+    reference: &str,
+    base_path: &Utf8PathBuf,
+    parser: &CooklangParser,
+) -> Result<f64> {
+    let Value::Number(value) = quantity.value() else {
+        bail!("Only numbers can be used to scale a referenced recipe");
+    };
+    let value = value.value();
+    let unit = quantity.unit().unwrap_or_default();
+
+    let metadata = recipe_metadata(base_path, reference, parser)?;
+    let converter = parser.converter();
+
+    if let Some(recipe_yield) = metadata.get("yield").and_then(|v| v.as_str_like()) {
+        let mut recipe_yield = parse_quantity(&recipe_yield)
+            .with_context(|| format!("Invalid yield in {reference}: '{recipe_yield}'"))?;
+
+        let compatible = match quantity.compatible_unit(&recipe_yield, converter) {
+            Ok(Some(unit)) => recipe_yield.convert(&unit, converter).is_ok(),
+            Ok(None) => true,
+            // allow "1 ball" for a yield of "6 balls"
+            Err(_) => recipe_yield.unit().is_some_and(|u| same_unit_name(u, unit)),
+        };
+        if compatible {
+            if let Value::Number(total) = recipe_yield.value() {
+                if total.value() > 0.0 {
+                    return Ok(value / total.value());
+                }
+            }
+        }
+    }
+
+    if ["serving", "servings", "portion", "portions"].contains(&unit.to_lowercase().as_str()) {
+        if let Some(servings) = metadata.servings().and_then(|s| s.first().copied()) {
+            return Ok(value / servings as f64);
+        }
+    }
+
+    bail!(
+        "The referenced recipe has no yield in '{unit}'. \
+         Add it to its metadata, like `yield: {value} {unit}`"
+    )
+}
+
+/// Compare unit names ignoring case and plurals
+fn same_unit_name(a: &str, b: &str) -> bool {
+    let singular = |s: &str| s.trim().to_lowercase().trim_end_matches('s').to_string();
+    singular(a) == singular(b)
+}
+
+/// Parses only the metadata of a recipe
+pub fn recipe_metadata(
+    base_path: &Utf8PathBuf,
+    name: &str,
+    parser: &CooklangParser,
+) -> Result<Metadata> {
+    let entry = get_recipe(base_path, name)?;
+    let path = entry.path().as_ref().context("Recipe has no path")?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read recipe file: {path}"))?;
+    parser
+        .parse_metadata(&content)
+        .into_output()
+        .with_context(|| format!("Invalid metadata in recipe: {path}"))
+}
+
+/// Parse quantities like `500 g`, `500%g`, `1/2 cup` or `3`
+pub fn parse_quantity(s: &str) -> Result<ScaledQuantity> {
+    let s = s.trim();
+    let (value, unit) = match s.split_once('%') {
+        Some((value, unit)) => (value.trim(), unit.trim()),
+        None => {
+            // the unit starts at the first letter
+            let split = s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len());
+            (s[..split].trim(), s[split..].trim())
+        }
+    };
+
+    let value = parse_number(value).with_context(|| format!("Invalid number in '{s}'"))?;
+    let unit = (!unit.is_empty()).then(|| unit.to_string());
+    Ok(ScaledQuantity::new(
+        Value::Number(Number::Regular(value)),
+        unit,
+    ))
+}
+
+/// The value of a quantity, `None` if it's text
+pub(crate) fn number(q: &ScaledQuantity) -> Option<f64> {
+    match q.value() {
+        Value::Number(n) => Some(n.value()),
+        _ => None,
+    }
+}
+
+/// Numbers like `2`, `1.5`, `1/2` or `1 1/2`
+fn parse_number(s: &str) -> Result<f64> {
+    let mut total = 0.0;
+    for part in s.split_whitespace() {
+        total += match part.split_once('/') {
+            Some((num, den)) => {
+                let num: f64 = num.parse()?;
+                let den: f64 = den.parse()?;
+                if den == 0.0 {
+                    bail!("Division by zero");
+                }
+                num / den
+            }
+            None => part.parse::<f64>()?,
+        };
+    }
+    if s.trim().is_empty() {
+        bail!("Missing number");
+    }
+    Ok(total)
+}
+
+/// Recursively finds all `.cook` files in a directory
+///
+/// Hidden files and directories are skipped. The result is sorted.
+pub fn find_recipe_files(dir: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = dir
+            .read_dir_utf8()
+            .with_context(|| format!("Failed to read directory: {dir}"))?;
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to read directory: {dir}"))?;
+            if entry.file_name().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path.to_path_buf());
+            } else if path.extension() == Some("cook") {
+                files.push(path.to_path_buf());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
 pub fn get_recipe(base_path: &Utf8PathBuf, name: &str) -> Result<RecipeEntry> {
     Ok(cooklang_find::get_recipe(
         vec![base_path.clone()],
         name.into(),
     )?)
 }
+
+/// Parses a recipe with the given parser and scales it
+///
+/// Warnings are discarded.
+pub fn parse_recipe(
+    content: &str,
+    parser: &CooklangParser,
+    scaling: Scaling,
+) -> Result<ScaledRecipe> {
+    let (recipe, _warnings) = parser.parse(content).into_result().map_err(|report| {
+        let errors = report
+            .errors()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow::anyhow!("Failed to parse recipe: {errors}")
+    })?;
+    let factor = scaling.factor(&recipe.metadata)?;
+    Ok(recipe.scale(factor, parser.converter()))
+}
+
+/// Reads the file of a recipe entry and parses it with [`parse_recipe`]
+pub fn parse_recipe_entry(
+    entry: &RecipeEntry,
+    parser: &CooklangParser,
+    scaling: Scaling,
+) -> Result<ScaledRecipe> {
+    let path = entry.path().as_ref().context("Recipe has no path")?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read recipe file: {path}"))?;
+    parse_recipe(&content, parser, scaling).with_context(|| format!("Invalid recipe: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantity(s: &str) -> (f64, Option<String>) {
+        let q = parse_quantity(s).unwrap();
+        (number(&q).unwrap(), q.unit().map(String::from))
+    }
+
+    #[test]
+    fn quantities() {
+        assert_eq!(quantity("500 g"), (500.0, Some("g".into())));
+        assert_eq!(quantity("500%g"), (500.0, Some("g".into())));
+        assert_eq!(quantity(" 1.5 % kg "), (1.5, Some("kg".into())));
+        assert_eq!(quantity("1/2 cup"), (0.5, Some("cup".into())));
+        assert_eq!(quantity("1 1/2 cups"), (1.5, Some("cups".into())));
+        assert_eq!(quantity("250g"), (250.0, Some("g".into())));
+        assert_eq!(quantity("3"), (3.0, None));
+        assert_eq!(quantity("6 large balls"), (6.0, Some("large balls".into())));
+
+        for invalid in ["", "g", "lots", "1/0 cup", "1/x", "1..5 g"] {
+            assert!(parse_quantity(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    /// Base path with referenced recipes, and a parser with the bundled units
+    fn references() -> (tempfile::TempDir, Utf8PathBuf, CooklangParser) {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+        for (name, content) in [
+            (
+                "Pizza Dough",
+                "---\nyield: 6 balls\nservings: 3\n---\nKnead @flour{500%g}.",
+            ),
+            ("Sauce", "---\nyield: 1 l\n---\nCook @tomatoes{800%g}."),
+            ("Plain", "Mix @flour{100%g}."),
+        ] {
+            std::fs::write(base_path.join(format!("{name}.cook")), content).unwrap();
+        }
+        (
+            dir,
+            base_path,
+            CooklangParser::new(cooklang::Extensions::all(), cooklang::Converter::bundled()),
+        )
+    }
+
+    #[test]
+    fn reference_yield() {
+        let (_dir, base_path, parser) = references();
+        let scale = |q: &str, reference: &str| {
+            reference_scale(&parse_quantity(q).unwrap(), reference, &base_path, &parser)
+        };
+
+        assert_eq!(scale("6 balls", "Pizza Dough").unwrap(), 1.0);
+        assert_eq!(scale("3 Ball", "Pizza Dough").unwrap(), 0.5);
+        // converted to the unit of the yield
+        assert_eq!(scale("500 ml", "Sauce").unwrap(), 0.5);
+        // servings when the unit is not the one of the yield
+        assert_eq!(scale("6 servings", "Pizza Dough").unwrap(), 2.0);
+
+        assert!(scale("2 cups", "Pizza Dough").is_err());
+        assert!(scale("500 g", "Sauce").is_err());
+        assert!(scale("2 servings", "Plain").is_err());
+        assert!(scale("1 ball", "Missing").is_err());
+
+        let text = ScaledQuantity::new(Value::Text("some".into()), None);
+        assert!(reference_scale(&text, "Pizza Dough", &base_path, &parser).is_err());
+    }
+
+    #[test]
+    fn scaling() {
+        let parse = |s: &str| s.parse::<Scaling>();
+        assert_eq!(parse("2").unwrap(), Scaling::Factor(2.0));
+        assert_eq!(parse(" 0.5 ").unwrap(), Scaling::Factor(0.5));
+        assert_eq!(parse("4p").unwrap(), Scaling::Servings(4));
+
+        for invalid in ["", "p", "4.5p", "-2p", "2x", "four"] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+
+        assert_eq!(
+            split_recipe_name_and_scaling_factor("Breakfast/Pancakes:4p"),
+            Some(("Breakfast/Pancakes", "4p"))
+        );
+        assert_eq!(split_recipe_name_and_scaling_factor("Pancakes"), None);
+    }
+
+    #[test]
+    fn invalid_scaling_target() {
+        let (_dir, base_path, parser) = references();
+        let extract = |entry: &str| {
+            let mut list = IngredientList::new();
+            extract_ingredients(
+                entry,
+                &mut list,
+                &mut BTreeMap::new(),
+                &base_path,
+                &parser,
+                false,
+                None,
+            )
+        };
+
+        assert!(extract("Plain:2").is_ok());
+        let err = extract("Plain:lots").unwrap_err();
+        assert!(format!("{err:#}").starts_with("Invalid scaling target for 'Plain'"));
+    }
+
+    #[test]
+    fn scaling_factor() {
+        let parser = CooklangParser::canonical();
+        let metadata = |s: &str| parser.parse_metadata(s).into_output().unwrap();
+        let four = metadata("---\nservings: 4\n---\n");
+        let none = metadata("Just a step.");
+
+        assert_eq!(Scaling::Factor(2.0).factor(&four).unwrap(), 2.0);
+        assert_eq!(Scaling::Factor(2.0).factor(&none).unwrap(), 2.0);
+        // `2` doubles the recipe, `2p` makes it for two
+        assert_eq!(Scaling::Servings(2).factor(&four).unwrap(), 0.5);
+        assert_eq!(Scaling::Servings(8).factor(&four).unwrap(), 2.0);
+
+        assert!(Scaling::Servings(2).factor(&none).is_err());
+        assert!(Scaling::Servings(0).factor(&four).is_err());
+        let zero = metadata("---\nservings: 0\n---\n");
+        assert!(Scaling::Servings(2).factor(&zero).is_err());
+    }
+
+    #[test]
+    fn aisle() {
+        let path = Utf8Path::new("aisle.conf");
+        let aisle = parse_aisle(path, "[Dairy]\nmilk\nbutter|unsalted butter\n").unwrap();
+        let info = aisle.ingredients_info();
+        assert_eq!(info["unsalted butter"].category, "Dairy");
+        assert_eq!(info["unsalted butter"].common_name, "butter");
+
+        assert!(parse_aisle(path, "milk\n[Dairy]\n").is_err());
+        assert!(parse_aisle(path, "[Dairy]\nmilk\n[Drinks]\nmilk\n").is_err());
+    }
+}
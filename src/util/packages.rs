@@ -0,0 +1,217 @@
+//! Rounding shopping list quantities to the packages stores sell
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use cooklang::{
+    ingredient_list::IngredientList,
+    quantity::{Number, Value},
+    Converter, ScaledQuantity,
+};
+use serde::Serialize;
+
+use super::{db::Db, number, units::with_units};
+
+/// Rounded quantities, by ingredient name
+///
+/// Only ingredients with packages in the db are here.
+#[derive(Debug, Default)]
+pub struct Packages(BTreeMap<String, Rounded>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Rounded {
+    pub packages: Vec<PackageCount>,
+    /// Quantities no package size fits, like a different unit
+    pub rest: Vec<ScaledQuantity>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageCount {
+    pub count: u32,
+    pub size: ScaledQuantity,
+    /// What will be left after using what's needed
+    pub leftover: ScaledQuantity,
+}
+
+impl PackageCount {
+    pub fn has_leftover(&self) -> bool {
+        number(&self.leftover).is_some_and(|v| v > 1e-6)
+    }
+}
+
+impl Packages {
+    /// Rounds every ingredient of the list with packages in the db
+    pub fn round(list: &IngredientList, db: &Db, converter: &Converter) -> Result<Self> {
+        let converter = with_units(converter);
+        let mut packages = Self::default();
+        for (name, quantities) in list.iter() {
+            let Some(shopping) = db.shopping(name)? else {
+                continue;
+            };
+            if shopping.packages.is_empty() {
+                continue;
+            }
+
+            let mut rounded = Rounded {
+                packages: Vec::new(),
+                rest: Vec::new(),
+            };
+            for q in quantities.iter() {
+                match round(q, &shopping.packages, &converter) {
+                    Some(count) => rounded.packages.push(count),
+                    None => rounded.rest.push(q.clone()),
+                }
+            }
+            packages.0.insert(name.clone(), rounded);
+        }
+        Ok(packages)
+    }
+
+    pub fn get(&self, ingredient: &str) -> Option<&Rounded> {
+        self.0.get(ingredient)
+    }
+}
+
+/// The package size with the least leftover, and then the fewest packages
+fn round(
+    needed: &ScaledQuantity,
+    sizes: &[ScaledQuantity],
+    converter: &Converter,
+) -> Option<PackageCount> {
+    sizes
+        .iter()
+        .filter_map(|size| {
+            let size_value = number(size).filter(|&v| v > 0.0)?;
+            let mut needed = needed.clone();
+            if let Some(unit) = size.compatible_unit(&needed, converter).ok()? {
+                needed.convert(&unit, converter).ok()?;
+            }
+            let needed_value = number(&needed).filter(|&v| v > 0.0)?;
+
+            // conversions are not exact, don't buy a package for 0.0000001 g
+            let count = (needed_value / size_value - 1e-6).ceil().max(1.0);
+            let leftover = (count * size_value - needed_value).max(0.0);
+            let package = PackageCount {
+                count: count as u32,
+                size: size.clone(),
+                leftover: ScaledQuantity::new(
+                    Value::Number(Number::Regular(leftover)),
+                    size.unit().map(String::from),
+                ),
+            };
+            // sizes can have different units, compare the leftover relative
+            // to what's needed
+            Some((leftover / needed_value, package))
+        })
+        .min_by(|(a_left, a), (b_left, b)| {
+            // converted leftovers are not exact either, so close ones are a tie
+            if (a_left - b_left).abs() < 1e-6 {
+                a.count.cmp(&b.count)
+            } else {
+                a_left.total_cmp(b_left)
+            }
+        })
+        .map(|(_, package)| package)
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::quantity::GroupedQuantity;
+
+    use super::*;
+    use crate::util::{db::DB_DIR, parse_quantity};
+
+    /// Count, size and leftover of the best package for `needed`
+    fn best(needed: &str, sizes: &[&str]) -> Option<(u32, String, String)> {
+        let converter = Converter::bundled();
+        let sizes = sizes
+            .iter()
+            .map(|s| parse_quantity(s).unwrap())
+            .collect::<Vec<_>>();
+        let needed = match parse_quantity(needed) {
+            Ok(q) => q,
+            Err(_) => ScaledQuantity::new(Value::Text(needed.into()), None),
+        };
+        round(&needed, &sizes, &converter)
+            .map(|p| (p.count, p.size.to_string(), p.leftover.to_string()))
+    }
+
+    fn package(count: u32, size: &str, leftover: &str) -> Option<(u32, String, String)> {
+        Some((count, size.to_string(), leftover.to_string()))
+    }
+
+    #[test]
+    fn least_leftover() {
+        assert_eq!(
+            best("1200 g", &["500 g", "1 kg"]),
+            package(3, "500 g", "300 g")
+        );
+        assert_eq!(
+            best("400 g", &["500 g", "1 kg"]),
+            package(1, "500 g", "100 g")
+        );
+        // same leftover, fewer packages
+        assert_eq!(
+            best("700 g", &["500 g", "1 kg"]),
+            package(1, "1 kg", "0.3 kg")
+        );
+        assert_eq!(best("7", &["6", "12"]), package(1, "12", "5"));
+    }
+
+    #[test]
+    fn exact_after_conversion() {
+        assert_eq!(best("1000 ml", &["1 l"]), package(1, "1 l", "0 l"));
+        let converter = Converter::bundled();
+        let count = round(
+            &parse_quantity("1500 ml").unwrap(),
+            &[parse_quantity("0.5 l").unwrap()],
+            &converter,
+        )
+        .unwrap();
+        assert_eq!(count.count, 3);
+        assert!(!count.has_leftover());
+    }
+
+    #[test]
+    fn no_fitting_size() {
+        // different physical quantities
+        assert_eq!(best("2 cups", &["500 g"]), None);
+        // unknown units only match themselves
+        assert_eq!(best("3 balls", &["500 g"]), None);
+        assert_eq!(
+            best("3 balls", &["4 balls"]),
+            package(1, "4 balls", "1 balls")
+        );
+        // with and without a unit
+        assert_eq!(best("2", &["500 g"]), None);
+        assert_eq!(best("200 g", &["6"]), None);
+        assert_eq!(best("some", &["500 g"]), None);
+        assert_eq!(best("0 g", &["500 g"]), None);
+        assert_eq!(best("200 g", &["0 g"]), None);
+    }
+
+    #[test]
+    fn round_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = camino::Utf8Path::from_path(dir.path()).unwrap();
+        let flour = base_path.join(DB_DIR).join("flour");
+        std::fs::create_dir_all(&flour).unwrap();
+        std::fs::write(flour.join("shopping.yml"), "packages: [500 g, 1 kg]").unwrap();
+
+        let converter = Converter::bundled();
+        let mut list = IngredientList::new();
+        for (name, quantity) in [("flour", "1200 g"), ("flour", "1 cup"), ("salt", "5 g")] {
+            let mut grouped = GroupedQuantity::empty();
+            grouped.add(&parse_quantity(quantity).unwrap(), &converter);
+            list.add_ingredient(name.to_string(), &grouped, &converter);
+        }
+
+        let packages = Packages::round(&list, &Db::new(base_path), &converter).unwrap();
+        let flour = packages.get("flour").unwrap();
+        assert_eq!(flour.packages.len(), 1);
+        assert_eq!(flour.packages[0].count, 3);
+        assert_eq!(flour.rest.len(), 1);
+        assert_eq!(flour.rest[0].to_string(), "1 cup");
+        assert!(packages.get("salt").is_none());
+    }
+}
@@ -0,0 +1,317 @@
+//! Ingredients already at home
+//!
+//! The pantry file is TOML. Ingredients can be grouped in tables, usually by
+//! where they are stored, and their value is what's left:
+//!
+//! ```toml
+//! salt = true # always there, never buy it
+//!
+//! [fridge]
+//! milk = "1 l"
+//! eggs = 6
+//!
+//! [cupboard]
+//! flour = "1.5%kg"
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    convert::Converter,
+    ingredient_list::IngredientList,
+    quantity::{GroupedQuantity, Number, Value},
+    ScaledQuantity,
+};
+
+use super::{number, parse_quantity};
+
+#[derive(Debug, Default)]
+pub struct Pantry {
+    /// By lowercase ingredient name
+    items: HashMap<String, Stock>,
+}
+
+#[derive(Debug, Clone)]
+enum Stock {
+    /// Enough for any recipe
+    Unlimited,
+    /// One for every place it's stored in
+    Quantities(Vec<ScaledQuantity>),
+}
+
+/// Result of [`Pantry::subtract`]
+pub struct Subtracted {
+    /// What still has to be bought
+    pub to_buy: IngredientList,
+    /// What the pantry stock covers
+    pub covered: IngredientList,
+}
+
+impl Pantry {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pantry file: {path}"))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse pantry file: {path}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(content)?;
+        let mut pantry = Self::default();
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(section) => {
+                    for (name, value) in section {
+                        pantry.insert(&name, value)?;
+                    }
+                }
+                value => pantry.insert(&key, value)?,
+            }
+        }
+        Ok(pantry)
+    }
+
+    fn insert(&mut self, name: &str, value: toml::Value) -> Result<()> {
+        let stock = match value {
+            toml::Value::Boolean(true) => Stock::Unlimited,
+            // explicitly out of stock
+            toml::Value::Boolean(false) => return Ok(()),
+            toml::Value::Integer(n) => Stock::Quantities(vec![ScaledQuantity::new(
+                Value::Number(Number::Regular(n as f64)),
+                None,
+            )]),
+            toml::Value::Float(n) => Stock::Quantities(vec![ScaledQuantity::new(
+                Value::Number(Number::Regular(n)),
+                None,
+            )]),
+            toml::Value::String(s) => Stock::Quantities(vec![
+                parse_quantity(&s).with_context(|| format!("Invalid quantity for '{name}'"))?
+            ]),
+            other => bail!(
+                "Invalid value for '{name}': expected a quantity or true, found {}",
+                other.type_str()
+            ),
+        };
+        let name = key(name);
+        match (self.items.get_mut(&name), stock) {
+            (Some(Stock::Quantities(current)), Stock::Quantities(q)) => current.extend(q),
+            (Some(Stock::Unlimited), _) => {}
+            (_, stock) => {
+                self.items.insert(name, stock);
+            }
+        }
+        Ok(())
+    }
+
+    /// Split a shopping list into what has to be bought and what is covered
+    ///
+    /// Quantities are subtracted when their units are compatible, converting
+    /// them if needed. Anything that can't be compared, like text quantities
+    /// or different physical quantities, stays in the list to buy.
+    ///
+    /// Names that only differ in case, like `Flour` and `flour`, share the
+    /// same stock.
+    pub fn subtract(&self, list: IngredientList, converter: &Converter) -> Subtracted {
+        let mut to_buy = IngredientList::new();
+        let mut covered = IngredientList::new();
+        // what is left of each stock after the previous ingredients
+        let mut left: HashMap<String, Vec<ScaledQuantity>> = HashMap::new();
+
+        for (name, quantity) in list {
+            let key = key(&name);
+            let Some(stock) = self.items.get(&key) else {
+                to_buy.add_ingredient(name, &quantity, converter);
+                continue;
+            };
+
+            let stock = match stock {
+                Stock::Unlimited => {
+                    covered.add_ingredient(name, &quantity, converter);
+                    continue;
+                }
+                Stock::Quantities(q) => left.entry(key).or_insert_with(|| q.clone()),
+            };
+
+            // only listed without a quantity, having some is enough
+            if quantity.is_empty() {
+                covered.add_ingredient(name, &quantity, converter);
+                continue;
+            }
+
+            let mut buy = GroupedQuantity::empty();
+            let mut used = GroupedQuantity::empty();
+            for needed in quantity.iter() {
+                let mut missing = Some(needed.clone());
+                for stock in stock.iter_mut() {
+                    let Some(needed) = &missing else { break };
+                    if let Some((still_missing, used_now, left)) =
+                        subtract(needed, stock, converter)
+                    {
+                        used.add(&used_now, converter);
+                        *stock = left;
+                        missing = still_missing;
+                    }
+                }
+                if let Some(missing) = missing {
+                    buy.add(&missing, converter);
+                }
+            }
+
+            if !buy.is_empty() {
+                to_buy.add_ingredient(name.clone(), &buy, converter);
+            }
+            if !used.is_empty() {
+                covered.add_ingredient(name, &used, converter);
+            }
+        }
+
+        Subtracted { to_buy, covered }
+    }
+}
+
+/// Name used to find the stock of an ingredient
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Subtract the stock from a needed quantity
+///
+/// Returns what is missing (if anything), what was used and what is left of
+/// the stock, all in the unit of `needed`. [`None`] if they can't be
+/// subtracted.
+fn subtract(
+    needed: &ScaledQuantity,
+    stock: &ScaledQuantity,
+    converter: &Converter,
+) -> Option<(Option<ScaledQuantity>, ScaledQuantity, ScaledQuantity)> {
+    let needed_value = number(needed)?;
+    if number(stock)? <= 0.0 {
+        return None;
+    }
+
+    let mut stock = stock.clone();
+    if let Some(unit) = needed.compatible_unit(&stock, converter).ok()? {
+        stock.convert(&unit, converter).ok()?;
+    }
+    let difference = needed_value - number(&stock)?;
+
+    let with_unit = |value: f64| {
+        ScaledQuantity::new(
+            Value::Number(Number::Regular(value)),
+            needed.unit().map(String::from),
+        )
+    };
+
+    // conversions are not exact, don't buy 0.0000001 g
+    if difference > 1e-6 {
+        Some((
+            Some(with_unit(difference)),
+            with_unit(needed_value - difference),
+            with_unit(0.0),
+        ))
+    } else {
+        Some((None, needed.clone(), with_unit((-difference).max(0.0))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ingredients with quantities like `500 g`, or no quantity if empty
+    fn list(items: &[(&str, &str)], converter: &Converter) -> IngredientList {
+        let mut list = IngredientList::new();
+        for (name, quantity) in items {
+            let mut grouped = GroupedQuantity::empty();
+            if !quantity.is_empty() {
+                grouped.add(&parse_quantity(quantity).unwrap(), converter);
+            }
+            list.add_ingredient(name.to_string(), &grouped, converter);
+        }
+        list
+    }
+
+    type Names = Vec<(String, String)>;
+
+    fn quantities(list: &IngredientList) -> Names {
+        list.iter()
+            .map(|(name, q)| (name.clone(), q.to_string()))
+            .collect()
+    }
+
+    fn subtract(pantry: &str, items: &[(&str, &str)]) -> (Names, Names) {
+        let converter = Converter::bundled();
+        let pantry = Pantry::parse(pantry).unwrap();
+        let result = pantry.subtract(list(items, &converter), &converter);
+        (quantities(&result.to_buy), quantities(&result.covered))
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Names {
+        items
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let pantry = Pantry::parse(
+            "salt = true\npepper = false\n[fridge]\nMilk = \"1 l\"\n[door]\nmilk = 0.5\n",
+        )
+        .unwrap();
+        assert!(matches!(pantry.items["salt"], Stock::Unlimited));
+        assert!(!pantry.items.contains_key("pepper"));
+        let Stock::Quantities(milk) = &pantry.items["milk"] else {
+            panic!("milk has quantities");
+        };
+        assert_eq!(milk.len(), 2);
+
+        assert!(Pantry::parse("flour = \"lots\"").is_err());
+        assert!(Pantry::parse("flour = [1, 2]").is_err());
+    }
+
+    #[test]
+    fn partial_stock() {
+        let (to_buy, covered) = subtract("flour = \"200 g\"", &[("flour", "500 g")]);
+        assert_eq!(to_buy, pairs(&[("flour", "300 g")]));
+        assert_eq!(covered, pairs(&[("flour", "200 g")]));
+    }
+
+    #[test]
+    fn converts_units() {
+        let (to_buy, covered) = subtract("flour = \"1 kg\"", &[("flour", "500 g")]);
+        assert!(to_buy.is_empty());
+        assert_eq!(covered, pairs(&[("flour", "500 g")]));
+    }
+
+    #[test]
+    fn stock_is_used_once_for_any_case() {
+        let (to_buy, covered) = subtract(
+            "flour = \"600 g\"",
+            &[("Flour", "500 g"), ("flour", "500 g")],
+        );
+        assert_eq!(to_buy, pairs(&[("flour", "400 g")]));
+        assert_eq!(covered, pairs(&[("Flour", "500 g"), ("flour", "100 g")]));
+    }
+
+    #[test]
+    fn unlimited_and_missing() {
+        let (to_buy, covered) = subtract(
+            "salt = true\npepper = false",
+            &[("salt", "1 tsp"), ("pepper", "1 tsp"), ("eggs", "2")],
+        );
+        assert_eq!(to_buy, pairs(&[("eggs", "2"), ("pepper", "1 tsp")]));
+        assert_eq!(covered, pairs(&[("salt", "1 tsp")]));
+    }
+
+    #[test]
+    fn incompatible_units_are_bought() {
+        let (to_buy, covered) = subtract(
+            "milk = \"1 l\"\neggs = 6",
+            &[("milk", "200 g"), ("eggs", ""), ("basil", "")],
+        );
+        assert_eq!(to_buy, pairs(&[("basil", ""), ("milk", "200 g")]));
+        assert_eq!(covered, pairs(&[("eggs", "")]));
+    }
+}
@@ -0,0 +1,268 @@
+//! Meal plan files
+//!
+//! A plan is a YAML file with days, the meals of each day and the recipes of
+//! each meal. Days and meals keep the order of the file:
+//!
+//! ```yaml
+//! Monday:
+//!   breakfast: Breakfast/Easy Pancakes
+//!   dinner:
+//!     - Neapolitan Pizza:4p     # like in the command line, `:2` to double it
+//!     - recipe: Salad
+//!       servings: 4
+//! Tuesday:
+//!   lunch: Leftover Soup
+//! ```
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use serde::Deserialize;
+
+use super::{split_recipe_name_and_scaling_factor, Scaling, RECIPE_SCALING_DELIMITER};
+
+#[derive(Debug)]
+pub struct Plan {
+    pub days: Vec<Day>,
+}
+
+#[derive(Debug)]
+pub struct Day {
+    pub name: String,
+    pub meals: Vec<Meal>,
+}
+
+#[derive(Debug)]
+pub struct Meal {
+    pub name: String,
+    pub recipes: Vec<PlannedRecipe>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRecipe {
+    /// Name or path, like in the command line
+    pub recipe: String,
+    pub scaling: Option<Scaling>,
+}
+
+/// A recipe as written in the file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRecipe {
+    Short(String),
+    Full {
+        recipe: String,
+        servings: Option<u32>,
+        scale: Option<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRecipes {
+    One(RawRecipe),
+    Many(Vec<RawRecipe>),
+}
+
+impl Plan {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan file: {path}"))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse plan file: {path}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let days: serde_yaml::Mapping = serde_yaml::from_str(content)?;
+
+        let mut plan = Plan { days: Vec::new() };
+        for (day, meals) in days {
+            let day = key_name(day)?;
+            let meals = match meals {
+                serde_yaml::Value::Mapping(meals) => meals,
+                serde_yaml::Value::Null => Default::default(),
+                _ => bail!("'{day}' must map meals to recipes"),
+            };
+
+            let mut day = Day {
+                name: day,
+                meals: Vec::new(),
+            };
+            for (meal, recipes) in meals {
+                let meal = key_name(meal)?;
+                let recipes = match recipes {
+                    serde_yaml::Value::Null => Vec::new(),
+                    recipes => match serde_yaml::from_value(recipes)
+                        .with_context(|| format!("Invalid recipes for {} {meal}", day.name))?
+                    {
+                        RawRecipes::One(r) => vec![r],
+                        RawRecipes::Many(r) => r,
+                    },
+                };
+                day.meals.push(Meal {
+                    name: meal,
+                    recipes: recipes
+                        .into_iter()
+                        .map(PlannedRecipe::try_from)
+                        .collect::<Result<_>>()?,
+                });
+            }
+            plan.days.push(day);
+        }
+        Ok(plan)
+    }
+
+    /// Every planned recipe, in order
+    pub fn recipes(&self) -> impl Iterator<Item = &PlannedRecipe> {
+        self.days
+            .iter()
+            .flat_map(|d| &d.meals)
+            .flat_map(|m| &m.recipes)
+    }
+}
+
+fn key_name(key: serde_yaml::Value) -> Result<String> {
+    match key {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        other => bail!("Invalid day or meal name: {other:?}"),
+    }
+}
+
+impl TryFrom<RawRecipe> for PlannedRecipe {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawRecipe) -> Result<Self> {
+        let planned = match raw {
+            RawRecipe::Short(entry) => match split_recipe_name_and_scaling_factor(&entry) {
+                Some((name, scaling)) => Self {
+                    recipe: name.to_string(),
+                    scaling: Some(
+                        scaling
+                            .parse()
+                            .with_context(|| format!("Invalid scaling for '{name}'"))?,
+                    ),
+                },
+                None => Self {
+                    recipe: entry.trim().to_string(),
+                    scaling: None,
+                },
+            },
+            RawRecipe::Full {
+                recipe,
+                servings,
+                scale,
+            } => {
+                let scaling = match (servings, scale) {
+                    (Some(_), Some(_)) => {
+                        bail!("'{recipe}' has both servings and scale, use only one")
+                    }
+                    (Some(servings), None) => Some(Scaling::Servings(servings)),
+                    (None, Some(scale)) => Some(Scaling::Factor(scale)),
+                    (None, None) => None,
+                };
+                Self { recipe, scaling }
+            }
+        };
+        Ok(planned)
+    }
+}
+
+impl PlannedRecipe {
+    /// Entry for [`extract_ingredients`](super::extract_ingredients)
+    pub fn shopping_list_entry(&self) -> String {
+        match self.scaling {
+            Some(Scaling::Servings(servings)) => {
+                format!("{}{RECIPE_SCALING_DELIMITER}{servings}p", self.recipe)
+            }
+            Some(Scaling::Factor(scale)) => {
+                format!("{}{RECIPE_SCALING_DELIMITER}{scale}", self.recipe)
+            }
+            None => self.recipe.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_and_meals_keep_their_order() {
+        let plan =
+            Plan::parse("Tuesday:\n  dinner: Soup\n  breakfast: Toast\nMonday:\n  lunch: Salad\n")
+                .unwrap();
+        let names = plan
+            .days
+            .iter()
+            .map(|d| {
+                (
+                    d.name.as_str(),
+                    d.meals.iter().map(|m| m.name.as_str()).collect(),
+                )
+            })
+            .collect::<Vec<(_, Vec<_>)>>();
+        assert_eq!(
+            names,
+            [
+                ("Tuesday", vec!["dinner", "breakfast"]),
+                ("Monday", vec!["lunch"])
+            ]
+        );
+    }
+
+    #[test]
+    fn recipe_forms() {
+        let plan = Plan::parse(
+            "1:\n  dinner:\n    - Pizza:4p\n    - Pasta:2\n    - Breakfast/Easy Pancakes\n    \
+             - recipe: Salad\n      servings: 4\n    - recipe: Soup\n      scale: 0.5\n",
+        )
+        .unwrap();
+        assert_eq!(plan.days[0].name, "1");
+        let recipes = plan
+            .recipes()
+            .map(|r| (r.recipe.as_str(), r.scaling))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recipes,
+            [
+                ("Pizza", Some(Scaling::Servings(4))),
+                ("Pasta", Some(Scaling::Factor(2.0))),
+                ("Breakfast/Easy Pancakes", None),
+                ("Salad", Some(Scaling::Servings(4))),
+                ("Soup", Some(Scaling::Factor(0.5))),
+            ]
+        );
+        let entries = plan
+            .recipes()
+            .map(PlannedRecipe::shopping_list_entry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                "Pizza:4p",
+                "Pasta:2",
+                "Breakfast/Easy Pancakes",
+                "Salad:4p",
+                "Soup:0.5"
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_days_and_meals() {
+        let plan = Plan::parse("Monday:\nTuesday:\n  lunch:\n").unwrap();
+        assert!(plan.days[0].meals.is_empty());
+        assert!(plan.days[1].meals[0].recipes.is_empty());
+        assert_eq!(plan.recipes().count(), 0);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Plan::parse("Monday: Soup").is_err());
+        assert!(Plan::parse("Monday:\n  lunch: Soup:lots").is_err());
+        assert!(
+            Plan::parse("Monday:\n  lunch:\n    recipe: Soup\n    servings: 2\n    scale: 2")
+                .is_err()
+        );
+        assert!(Plan::parse("[Monday]: \n").is_err());
+    }
+}
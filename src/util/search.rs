@@ -0,0 +1,416 @@
+//! Structured search over parsed recipes
+//!
+//! A query is a list of space separated terms. Plain words are matched
+//! against the recipe name and text, while `key:value` filters are evaluated
+//! against the parsed recipe:
+//!
+//! ```text
+//! ingredient:garlic -ingredient:pork tag:vegan time<30m servings>=4 cookware:wok path:Breakfast/
+//! ```
+//!
+//! Any term can be negated with a leading `-` and values with spaces can be
+//! quoted, like `ingredient:"olive oil"`.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{metadata::RecipeTime, CooklangParser, Metadata};
+use serde::{Deserialize, Serialize};
+
+/// What a query is evaluated against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeInfo {
+    /// Path relative to the base path
+    pub path: String,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub ingredients: Vec<String>,
+    pub cookware: Vec<String>,
+    pub servings: Option<u32>,
+    /// Total time in minutes
+    pub total_time: Option<u32>,
+    /// Lowercase content of the file, for free text search
+    pub text: String,
+    pub metadata: Metadata,
+}
+
+impl RecipeInfo {
+    /// Extract the searchable information of a recipe file
+    ///
+    /// Recipes with errors are still searchable by name, path and text.
+    pub fn new(relative_path: &Utf8Path, content: &str, parser: &CooklangParser) -> Self {
+        let mut info = Self {
+            path: relative_path.as_str().replace('\\', "/"),
+            name: relative_path.file_stem().unwrap_or_default().to_string(),
+            tags: Vec::new(),
+            ingredients: Vec::new(),
+            cookware: Vec::new(),
+            servings: None,
+            total_time: None,
+            text: content.to_lowercase(),
+            metadata: Metadata::default(),
+        };
+
+        let (recipe, _) = parser.parse(content).into_tuple();
+        let Some(recipe) = recipe else {
+            if let Some(metadata) = parser.parse_metadata(content).into_output() {
+                info.metadata = metadata;
+            }
+            return info;
+        };
+
+        let meta = &recipe.metadata;
+        info.tags = meta
+            .tags()
+            .unwrap_or_default()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        info.servings = meta.servings().and_then(|s| s.first().copied());
+        info.total_time = meta.time(parser.converter()).map(RecipeTime::total);
+        info.ingredients = recipe
+            .ingredients
+            .iter()
+            .map(|i| i.name.to_lowercase())
+            .collect();
+        info.cookware = recipe
+            .cookware
+            .iter()
+            .map(|c| c.name.to_lowercase())
+            .collect();
+        info.ingredients.sort();
+        info.ingredients.dedup();
+        info.cookware.sort();
+        info.cookware.dedup();
+        info.metadata = recipe.metadata;
+
+        info
+    }
+
+    /// Title from the metadata or the file name
+    pub fn title(&self) -> &str {
+        self.metadata.title().unwrap_or(&self.name)
+    }
+}
+
+/// Parsed search query
+#[derive(Debug, Clone)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Text(String),
+    Name(String),
+    Ingredient(String),
+    Cookware(String),
+    Tag(String),
+    Path(String),
+    Time(Cmp, u32),
+    Servings(Cmp, u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    fn eval(self, a: u32, b: u32) -> bool {
+        match self {
+            Cmp::Lt => a < b,
+            Cmp::Le => a <= b,
+            Cmp::Eq => a == b,
+            Cmp::Ge => a >= b,
+            Cmp::Gt => a > b,
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let terms = split_terms(s)?
+            .into_iter()
+            .map(|t| parse_term(&t))
+            .collect::<Result<_>>()?;
+        Ok(Self { terms })
+    }
+}
+
+/// Split by whitespace, keeping quoted parts together and removing the quotes
+fn split_terms(s: &str) -> Result<Vec<String>> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        bail!("Unclosed quote in search query");
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Ok(terms)
+}
+
+fn parse_term(term: &str) -> Result<Term> {
+    let (negated, term) = match term.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, term),
+    };
+
+    let key_len = term
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(term.len());
+    let (key, rest) = term.split_at(key_len);
+
+    let (cmp, value) = if let Some(v) = rest.strip_prefix("<=") {
+        (Cmp::Le, v)
+    } else if let Some(v) = rest.strip_prefix(">=") {
+        (Cmp::Ge, v)
+    } else if let Some(v) = rest.strip_prefix('<') {
+        (Cmp::Lt, v)
+    } else if let Some(v) = rest.strip_prefix('>') {
+        (Cmp::Gt, v)
+    } else if let Some(v) = rest.strip_prefix(':').or(rest.strip_prefix('=')) {
+        (Cmp::Eq, v)
+    } else {
+        return Ok(Term {
+            negated,
+            filter: Filter::Text(term.to_lowercase()),
+        });
+    };
+
+    if key.is_empty() {
+        return Ok(Term {
+            negated,
+            filter: Filter::Text(term.to_lowercase()),
+        });
+    }
+
+    let text_value = |name: &str| {
+        if value.is_empty() {
+            bail!("Missing value for '{name}' in search query");
+        }
+        if !matches!(cmp, Cmp::Eq) {
+            bail!("'{name}' can only be used with ':'");
+        }
+        Ok(value.to_lowercase())
+    };
+
+    let filter = match key.to_lowercase().as_str() {
+        "name" => Filter::Name(text_value(key)?),
+        "ingredient" | "i" => Filter::Ingredient(text_value(key)?),
+        "cookware" => Filter::Cookware(text_value(key)?),
+        "tag" => Filter::Tag(text_value(key)?),
+        "path" => Filter::Path(text_value(key)?),
+        "time" => Filter::Time(cmp, parse_minutes(value)?),
+        "servings" => Filter::Servings(
+            cmp,
+            value
+                .parse()
+                .with_context(|| format!("Invalid servings in search query: '{value}'"))?,
+        ),
+        _ => bail!(
+            "Unknown search filter '{key}'. \
+             Use ingredient, cookware, tag, path, name, time or servings"
+        ),
+    };
+
+    Ok(Term { negated, filter })
+}
+
+/// Minutes from `30`, `30m`, `1h`, `1h30m`...
+fn parse_minutes(value: &str) -> Result<u32> {
+    if let Ok(minutes) = value.parse() {
+        return Ok(minutes);
+    }
+    let duration = humantime::parse_duration(value)
+        .with_context(|| format!("Invalid time in search query: '{value}'"))?;
+    Ok((duration.as_secs() / 60) as u32)
+}
+
+impl Query {
+    /// Relevance of a recipe for the query, [`None`] if it doesn't match
+    ///
+    /// Every filter has to match. Plain words score like the old text search:
+    /// a name match is worth the most, then the number of occurrences in the
+    /// text.
+    pub fn score(&self, recipe: &RecipeInfo) -> Option<f64> {
+        let mut score = 0.0;
+
+        let words = self
+            .terms
+            .iter()
+            .filter_map(|t| match &t.filter {
+                Filter::Text(w) if !t.negated => Some(w.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !words.is_empty() {
+            let text_score = text_score(recipe, &words);
+            if text_score == 0.0 {
+                return None;
+            }
+            score += text_score;
+        }
+
+        for term in &self.terms {
+            let matches = match &term.filter {
+                Filter::Text(w) => {
+                    if !term.negated {
+                        continue;
+                    }
+                    recipe.name.to_lowercase().contains(w) || recipe.text.contains(w)
+                }
+                Filter::Name(n) => recipe.name.to_lowercase().contains(n),
+                Filter::Ingredient(i) => recipe.ingredients.iter().any(|x| x.contains(i)),
+                Filter::Cookware(c) => recipe.cookware.iter().any(|x| x.contains(c)),
+                Filter::Tag(t) => recipe.tags.iter().any(|x| x.to_lowercase() == *t),
+                Filter::Path(p) => recipe.path.to_lowercase().starts_with(p),
+                Filter::Time(cmp, m) => recipe.total_time.is_some_and(|t| cmp.eval(t, *m)),
+                Filter::Servings(cmp, s) => recipe.servings.is_some_and(|x| cmp.eval(x, *s)),
+            };
+            if matches == term.negated {
+                return None;
+            }
+            if !term.negated {
+                score += 1.0;
+            }
+        }
+
+        Some(score.max(1.0))
+    }
+
+    /// Matching recipes, most relevant first
+    pub fn search<'a>(&self, recipes: &'a [RecipeInfo]) -> Vec<(&'a RecipeInfo, f64)> {
+        let mut results = recipes
+            .iter()
+            .filter_map(|r| self.score(r).map(|s| (r, s)))
+            .collect::<Vec<_>>();
+        results.sort_by(|(a, sa), (b, sb)| {
+            sb.total_cmp(sa)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        results
+    }
+}
+
+fn text_score(recipe: &RecipeInfo, words: &[&str]) -> f64 {
+    let mut score = 0.0;
+
+    let phrase = words.join(" ");
+    let name = recipe.name.to_lowercase();
+    if name == phrase {
+        score += 20.0;
+    } else if name.contains(&phrase) {
+        score += 10.0;
+    }
+
+    let matches = words
+        .iter()
+        .map(|w| recipe.text.matches(w).count())
+        .sum::<usize>();
+    if matches > 0 {
+        score += 1.0 + (0.1 * matches as f64).min(5.0);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASTA: &str = "---
+servings: 4
+time: 25 min
+tags: [vegan, quick]
+---
+Boil @spaghetti{200%g} in a #pot{}. Add @olive oil{2%tbsp} and @garlic{2%cloves}.
+";
+
+    fn info(name: &str, content: &str) -> RecipeInfo {
+        let path = Utf8Path::new("Dinner").join(format!("{name}.cook"));
+        RecipeInfo::new(&path, content, &CooklangParser::canonical())
+    }
+
+    fn matches(query: &str) -> bool {
+        let query: Query = query.parse().unwrap();
+        query.score(&info("Garlic Pasta", PASTA)).is_some()
+    }
+
+    #[test]
+    fn filters() {
+        assert!(matches("ingredient:garlic"));
+        assert!(matches("i:\"olive oil\""));
+        assert!(!matches("ingredient:pork"));
+        assert!(matches("-ingredient:pork"));
+        assert!(matches("cookware:pot tag:VEGAN"));
+        assert!(!matches("tag:veg"));
+        assert!(matches("path:dinner/"));
+        assert!(matches("name:pasta"));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(matches("time<30m"));
+        assert!(matches("time<=25"));
+        assert!(!matches("time>1h"));
+        assert!(matches("servings>=4"));
+        assert!(!matches("servings<4"));
+        assert!(matches("servings:4"));
+    }
+
+    #[test]
+    fn text() {
+        assert!(matches("spaghetti"));
+        assert!(!matches("risotto"));
+        // any of the words is enough
+        assert!(matches("spaghetti risotto"));
+        assert!(matches("-risotto"));
+        // a colon without a key is just text
+        assert!(!matches(":garlic"));
+    }
+
+    #[test]
+    fn name_scores_more_than_text() {
+        let query: Query = "garlic pasta".parse().unwrap();
+        let by_name = query.score(&info("Garlic Pasta", PASTA)).unwrap();
+        let by_text = query.score(&info("Dinner", PASTA)).unwrap_or(0.0);
+        assert!(by_name > by_text);
+    }
+
+    #[test]
+    fn errors() {
+        assert!("ingredient:\"olive oil".parse::<Query>().is_err());
+        assert!("color:red".parse::<Query>().is_err());
+        assert!("ingredient:".parse::<Query>().is_err());
+        assert!("tag>vegan".parse::<Query>().is_err());
+        assert!("time<soon".parse::<Query>().is_err());
+        assert!("servings>many".parse::<Query>().is_err());
+    }
+}
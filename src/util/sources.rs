@@ -0,0 +1,54 @@
+//! Which recipes contributed each ingredient of a shopping list
+
+use std::collections::BTreeMap;
+
+use cooklang::{ingredient_list::IngredientList, Converter, ScaledQuantity, ScaledRecipe};
+use serde::Serialize;
+
+use super::units::Units;
+
+/// Contributions of every recipe, by ingredient name
+#[derive(Debug, Default)]
+pub struct Sources(BTreeMap<String, Vec<Source>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Source {
+    /// Path of the recipe, relative to the base path
+    pub recipe: String,
+    /// Quantity the recipe needs, already scaled
+    pub quantity: Vec<ScaledQuantity>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the ingredients of a recipe, listed like in
+    /// [`IngredientList::add_recipe`]
+    pub fn add_recipe(
+        &mut self,
+        path: &str,
+        recipe: &ScaledRecipe,
+        converter: &Converter,
+        list_references: bool,
+    ) {
+        for (name, quantity) in IngredientList::from_recipe(recipe, converter, list_references) {
+            self.0.entry(name).or_default().push(Source {
+                recipe: path.to_string(),
+                quantity: quantity.into_vec(),
+            });
+        }
+    }
+
+    /// Recipes that contributed an ingredient, in the order they were added
+    pub fn get(&self, ingredient: &str) -> &[Source] {
+        self.0.get(ingredient).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn convert(&mut self, units: Units, converter: &Converter) {
+        for source in self.0.values_mut().flatten() {
+            units.convert_quantities(&mut source.quantity, converter);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Converting quantities to another unit system
+
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use cooklang::{
+    convert::System, ingredient_list::IngredientList, Converter, CooklangParser, Extensions,
+    ScaledQuantity, ScaledRecipe,
+};
+use serde::Deserialize;
+
+/// Unit system to show quantities in
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// As written in the recipe
+    #[default]
+    Original,
+    Metric,
+    Imperial,
+}
+
+impl Units {
+    fn system(self) -> Option<System> {
+        match self {
+            Self::Original => None,
+            Self::Metric => Some(System::Metric),
+            Self::Imperial => Some(System::Imperial),
+        }
+    }
+
+    /// Parser for recipes that will be converted with
+    /// [`convert_recipe`](Self::convert_recipe)
+    ///
+    /// Inline quantities are enabled, so temperatures in the steps are found
+    /// too, and the bundled units are used when none are configured.
+    pub fn parser(self, parser: &CooklangParser) -> Cow<'_, CooklangParser> {
+        let extensions = parser.extensions() | Extensions::INLINE_QUANTITIES;
+        if self == Self::Original
+            || (extensions == parser.extensions() && parser.converter().unit_count() > 0)
+        {
+            return Cow::Borrowed(parser);
+        }
+        let converter = with_units(parser.converter()).into_owned();
+        Cow::Owned(CooklangParser::new(extensions, converter))
+    }
+
+    /// Converts every quantity of the recipe, in ingredients, timers and
+    /// inline quantities like temperatures in the steps
+    ///
+    /// The recipe has to be parsed with [`parser`](Self::parser) for the
+    /// inline quantities to be there.
+    ///
+    /// Each quantity uses the best unit of the system for its value. The ones
+    /// that can't be converted, like text or unknown units, are kept as they
+    /// are.
+    pub fn convert_recipe(self, recipe: &mut ScaledRecipe, converter: &Converter) {
+        let Some(system) = self.system() else {
+            return;
+        };
+        let converter = with_units(converter);
+        for err in recipe.convert(system, &converter) {
+            tracing::debug!("Quantity not converted: {err}");
+        }
+    }
+
+    /// Converts quantities one by one, without merging them
+    pub fn convert_quantities(self, quantities: &mut [ScaledQuantity], converter: &Converter) {
+        if let Some(system) = self.system() {
+            convert_all(quantities, system, &with_units(converter));
+        }
+    }
+
+    /// Converts and merges again the quantities of a shopping list
+    pub fn convert_list(self, list: IngredientList, converter: &Converter) -> IngredientList {
+        let Some(system) = self.system() else {
+            return list;
+        };
+        let converter = with_units(converter);
+
+        let mut converted = IngredientList::new();
+        for (name, quantities) in list {
+            let mut grouped = cooklang::quantity::GroupedQuantity::empty();
+            let mut quantities = quantities.into_vec();
+            convert_all(&mut quantities, system, &converter);
+            for q in &quantities {
+                grouped.add(q, &converter);
+            }
+            converted.add_ingredient(name, &grouped, &converter);
+        }
+        converted
+    }
+}
+
+fn convert_all(quantities: &mut [ScaledQuantity], system: System, converter: &Converter) {
+    for q in quantities {
+        if let Err(err) = q.convert(system, converter) {
+            tracing::debug!("Quantity not converted: {err}");
+        }
+    }
+}
+
+/// The parser converter, or the bundled units when it has none configured
+pub fn with_units(converter: &Converter) -> Cow<'_, Converter> {
+    if converter.unit_count() == 0 {
+        Cow::Owned(Converter::bundled())
+    } else {
+        Cow::Borrowed(converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::quantity::Value;
+
+    use super::*;
+    use crate::util::{parse_quantity, parse_recipe, Scaling};
+
+    fn convert(units: Units, content: &str) -> ScaledRecipe {
+        let canonical = CooklangParser::canonical();
+        let parser = units.parser(&canonical);
+        let mut recipe = parse_recipe(content, &parser, Scaling::Factor(1.0)).unwrap();
+        units.convert_recipe(&mut recipe, parser.converter());
+        recipe
+    }
+
+    #[test]
+    fn recipe() {
+        let content = "Heat to 200 °C and add @flour{1%kg} and @salt{some} for ~{10%min}.";
+
+        let recipe = convert(Units::Imperial, content);
+        let flour = recipe.ingredients[0].quantity.as_ref().unwrap();
+        assert_eq!(flour.unit(), Some("oz"));
+        assert_eq!(
+            recipe.ingredients[1].quantity.as_ref().unwrap().value(),
+            &Value::Text("some".into())
+        );
+        assert_eq!(
+            recipe.timers[0].quantity.as_ref().unwrap().to_string(),
+            "10 min"
+        );
+        assert_eq!(recipe.inline_quantities.len(), 1);
+        assert_eq!(recipe.inline_quantities[0].unit(), Some("°F"));
+
+        let recipe = convert(Units::Original, content);
+        assert_eq!(
+            recipe.ingredients[0].quantity.as_ref().unwrap().to_string(),
+            "1 kg"
+        );
+        assert!(recipe.inline_quantities.is_empty());
+    }
+
+    #[test]
+    fn quantities() {
+        let mut quantities = ["16 oz", "2 cups", "3 pinches"].map(|q| parse_quantity(q).unwrap());
+        Units::Metric.convert_quantities(&mut quantities, &Converter::empty());
+        assert_eq!(quantities[0].unit(), Some("g"));
+        assert_eq!(quantities[1].unit(), Some("ml"));
+        assert_eq!(quantities[2].to_string(), "3 pinches");
+    }
+
+    #[test]
+    fn list() {
+        let converter = Converter::bundled();
+        let mut list = IngredientList::new();
+        for q in ["500 g", "1 kg", "1 lb"] {
+            let mut grouped = cooklang::quantity::GroupedQuantity::empty();
+            grouped.add(&parse_quantity(q).unwrap(), &converter);
+            list.add_ingredient("flour".into(), &grouped, &converter);
+        }
+
+        let list = Units::Imperial.convert_list(list, &Converter::empty());
+        let list = list.into_iter().collect::<Vec<_>>();
+        assert_eq!(list.len(), 1);
+        let flour = list[0].1.clone().into_vec();
+        assert_eq!(flour.len(), 1);
+        assert_eq!(flour[0].unit(), Some("oz"));
+        assert_eq!(crate::util::number(&flour[0]).unwrap().round(), 69.0);
+    }
+}
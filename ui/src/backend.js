@@ -23,3 +23,10 @@ export const fetchShoppingList = (async (recipes) => {
 
     return await response.json();
 })
+
+
+export const subscribeToChanges = ((callback) => {
+    const events = new EventSource(`/api/events`);
+    events.addEventListener("change", (event) => callback(JSON.parse(event.data)));
+    return events;
+})
@@ -2,6 +2,9 @@ import { writable } from 'svelte/store';
 
 export const fileTree = writable();
 
+// Time of the last change to the recipe files, pushed by the server
+export const lastChange = writable(0);
+
 export function convertPathsIntoTree(data) {
     // If we're given the root node directly, return it
     if (data.children) {